@@ -0,0 +1,357 @@
+//! A hex grid in cube coordinates.
+//!
+//! Hexes are flat-topped, so the six neighbours lie north, north-east,
+//! south-east, south, south-west and north-west. We store the axial `q`
+//! (increasing to the east) and `r` (increasing to the south); the third
+//! cube coordinate is always `-q - r`.
+
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Hex {
+    pub q: isize,
+    pub r: isize,
+}
+
+impl Direction {
+    /// All six directions, clockwise from north.
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::NorthWest,
+    ];
+
+    /// The offset of a single step in this direction.
+    pub fn offset(self) -> Hex {
+        match self {
+            Direction::North => Hex::new(0, -1),
+            Direction::NorthEast => Hex::new(1, -1),
+            Direction::SouthEast => Hex::new(1, 0),
+            Direction::South => Hex::new(0, 1),
+            Direction::SouthWest => Hex::new(-1, 1),
+            Direction::NorthWest => Hex::new(-1, 0),
+        }
+    }
+
+    /// This direction turned `steps` sixths of a turn clockwise.
+    pub fn rotate(self, steps: isize) -> Direction {
+        Direction::ALL[(self as isize + steps).rem_euclid(6) as usize]
+    }
+
+    pub fn opposite(self) -> Direction {
+        self.rotate(3)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "n" => Ok(Direction::North),
+            "ne" => Ok(Direction::NorthEast),
+            "nw" => Ok(Direction::NorthWest),
+            "s" => Ok(Direction::South),
+            "se" => Ok(Direction::SouthEast),
+            "sw" => Ok(Direction::SouthWest),
+            other => Err(format!("unexpected direction {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Direction::North => "n",
+            Direction::NorthEast => "ne",
+            Direction::SouthEast => "se",
+            Direction::South => "s",
+            Direction::SouthWest => "sw",
+            Direction::NorthWest => "nw",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Hex {
+    pub fn new(q: isize, r: isize) -> Self {
+        Hex { q, r }
+    }
+
+    /// The third cube coordinate.
+    pub fn s(&self) -> isize {
+        -self.q - self.r
+    }
+
+    /// Converts from doubled coordinates, where a step north is `(0, 2)`
+    /// and a step north-east is `(1, 1)`. Panics if `x + y` is odd, since
+    /// that isn't the centre of a hex.
+    pub fn from_doubled(x: isize, y: isize) -> Self {
+        assert!((x + y) % 2 == 0, "({}, {}) is not a doubled coordinate", x, y);
+        Hex::new(x, (-y - x) / 2)
+    }
+
+    pub fn to_doubled(&self) -> (isize, isize) {
+        (self.q, -2 * self.r - self.q)
+    }
+
+    pub fn neighbour(&self, direction: Direction) -> Hex {
+        *self + direction.offset()
+    }
+
+    pub fn neighbours(&self) -> [Hex; 6] {
+        let mut out = [*self; 6];
+        for (hex, dir) in out.iter_mut().zip(Direction::ALL.iter()) {
+            *hex = self.neighbour(*dir);
+        }
+        out
+    }
+
+    /// The distance from the origin, in steps.
+    pub fn length(&self) -> usize {
+        (self.q.abs().max(self.r.abs()).max(self.s().abs())) as usize
+    }
+
+    pub fn distance(&self, other: Hex) -> usize {
+        (*self - other).length()
+    }
+
+    /// This hex rotated `steps` sixths of a turn clockwise about the origin.
+    pub fn rotate(&self, steps: isize) -> Hex {
+        let (mut q, mut r, mut s) = (self.q, self.r, self.s());
+        for _ in 0..steps.rem_euclid(6) {
+            let (nq, nr, ns) = (-r, -s, -q);
+            q = nq;
+            r = nr;
+            s = ns;
+        }
+        debug_assert_eq!(q + r + s, 0);
+        Hex::new(q, r)
+    }
+
+    /// This hex rotated about `center` rather than the origin.
+    pub fn rotate_about(&self, center: Hex, steps: isize) -> Hex {
+        (*self - center).rotate(steps) + center
+    }
+
+    /// Mirrors across the axis through the origin on which `q` is
+    /// constant; that is, swaps `r` and `s`.
+    pub fn reflect_q(&self) -> Hex {
+        Hex::new(self.q, self.s())
+    }
+
+    /// Mirrors across the `r` axis, swapping `q` and `s`.
+    pub fn reflect_r(&self) -> Hex {
+        Hex::new(self.s(), self.r)
+    }
+
+    /// Mirrors across the `s` axis, swapping `q` and `r`.
+    pub fn reflect_s(&self) -> Hex {
+        Hex::new(self.r, self.q)
+    }
+
+    /// Every hex exactly `radius` steps away, clockwise from the north.
+    pub fn ring(&self, radius: usize) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![*self]
+        }
+        let mut out = Vec::with_capacity(6 * radius);
+        let mut hex = *self + Direction::North.offset() * radius as isize;
+        for i in 0..6 {
+            let dir = Direction::SouthEast.rotate(i);
+            for _ in 0..radius {
+                out.push(hex);
+                hex = hex.neighbour(dir);
+            }
+        }
+        out
+    }
+
+    /// Every hex within `radius` steps, ordered by ring and then
+    /// clockwise within each ring.
+    pub fn spiral(&self, radius: usize) -> Vec<Hex> {
+        (0..radius + 1).flat_map(|r| self.ring(r)).collect()
+    }
+
+    /// A shortest path from here to `other`. Every shortest path uses at
+    /// most two adjacent directions; the canonical one takes all the steps
+    /// in the first direction (in `Direction::ALL` order) before the second.
+    pub fn path_to(&self, other: Hex) -> Vec<Direction> {
+        let delta = other - *self;
+        for i in 0..6 {
+            let (d1, d2) = (Direction::ALL[i], Direction::ALL[(i + 1) % 6]);
+            let (o1, o2) = (d1.offset(), d2.offset());
+            // solve a * o1 + b * o2 == delta; adjacent offsets have det ±1
+            let det = o1.q * o2.r - o2.q * o1.r;
+            let a = (delta.q * o2.r - o2.q * delta.r) / det;
+            let b = (o1.q * delta.r - delta.q * o1.r) / det;
+            if a >= 0 && b >= 0 {
+                let (first, second) = if i == 5 { ((d2, b), (d1, a)) } else { ((d1, a), (d2, b)) };
+                let mut path = vec![first.0; first.1 as usize];
+                path.extend(vec![second.0; second.1 as usize]);
+                return path
+            }
+        }
+        unreachable!("every hex lies in some sextant")
+    }
+
+    /// The hexes on a straight line from here to `other`, inclusive.
+    pub fn line_to(&self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+        if n == 0 {
+            return vec![*self]
+        }
+        // nudge off the exact midpoint between hexes so ties break consistently
+        let (aq, ar) = (self.q as f64 + 1e-6, self.r as f64 + 1e-6);
+        let (bq, br) = (other.q as f64 + 1e-6, other.r as f64 + 1e-6);
+        (0..n + 1)
+            .map(|i| {
+                let t = i as f64 / n as f64;
+                round_cube(aq + (bq - aq) * t, ar + (br - ar) * t)
+            })
+            .collect()
+    }
+}
+
+/// The distance from `start` after each step of `directions`.
+pub fn distance_profile(start: Hex, directions: &[Direction]) -> Vec<usize> {
+    let mut pos = start;
+    directions.iter()
+        .map(|dir| {
+            pos = pos.neighbour(*dir);
+            pos.distance(start)
+        })
+        .collect()
+}
+
+/// Formats directions the way they're parsed: comma-separated.
+pub fn format_path(directions: &[Direction]) -> String {
+    directions.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// Rounds fractional cube coordinates to the nearest hex.
+fn round_cube(q: f64, r: f64) -> Hex {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    Hex::new(rq as isize, rr as isize)
+}
+
+impl Add for Hex {
+    type Output = Hex;
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Neg for Hex {
+    type Output = Hex;
+    fn neg(self) -> Hex {
+        Hex::new(-self.q, -self.r)
+    }
+}
+
+impl Mul<isize> for Hex {
+    type Output = Hex;
+    fn mul(self, k: isize) -> Hex {
+        Hex::new(self.q * k, self.r * k)
+    }
+}
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.q, self.r, self.s())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubled_round_trip() {
+        let doubled = [(0, 2), (1, 1), (1, -1), (0, -2), (-1, -1), (-1, 1)];
+        for (dir, &(x, y)) in Direction::ALL.iter().zip(doubled.iter()) {
+            assert_eq!(Hex::from_doubled(x, y), dir.offset());
+            assert_eq!(dir.offset().to_doubled(), (x, y));
+        }
+        assert_eq!(Hex::from_doubled(3, 7).length(), 5);
+        assert_eq!(Hex::from_doubled(-6, 0).length(), 6);
+    }
+
+    #[test]
+    fn rotation_and_reflection() {
+        let ne = Direction::NorthEast.offset();
+        assert_eq!(Direction::North.offset().rotate(1), ne);
+        assert_eq!(ne.rotate(-1), Direction::North.offset());
+        assert_eq!(ne.rotate(6), ne);
+        assert_eq!(Direction::SouthWest.opposite(), Direction::NorthEast);
+        let h = Hex::new(3, -1);
+        assert_eq!(h.rotate_about(Hex::new(1, 1), 3), Hex::new(-1, 3));
+        assert_eq!(h.reflect_q().reflect_q(), h);
+        assert_eq!(Direction::North.offset().reflect_q(), Direction::South.offset());
+        for &reflected in &[h.reflect_q(), h.reflect_r(), h.reflect_s()] {
+            assert_eq!(reflected.length(), h.length());
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let parse = |s: &str| s.split(',').map(|d| d.parse::<Direction>().unwrap()).collect::<Vec<_>>();
+        let end = |s: &str| parse(s).iter().fold(Hex::default(), |h, d| h.neighbour(*d));
+        let origin = Hex::default();
+        assert_eq!(format_path(&origin.path_to(end("ne,ne,s,s"))), "se,se");
+        assert_eq!(format_path(&origin.path_to(end("se,sw,se,sw,sw"))), "s,s,sw");
+        assert_eq!(format_path(&origin.path_to(end("nw,n,nw"))), "n,nw,nw");
+        assert!(origin.path_to(origin).is_empty());
+        for hex in origin.spiral(4) {
+            let path = origin.path_to(hex);
+            assert_eq!(path.len(), hex.length());
+            assert_eq!(path.iter().fold(origin, |h, d| h.neighbour(*d)), hex);
+        }
+        assert_eq!(distance_profile(origin, &parse("ne,ne,sw,sw,s")), vec![1, 2, 1, 0, 1]);
+    }
+
+    #[test]
+    fn rings_and_lines() {
+        let origin = Hex::default();
+        assert_eq!(origin.ring(0), vec![origin]);
+        assert_eq!(origin.ring(1), Direction::ALL.iter().map(|d| d.offset()).collect::<Vec<_>>());
+        let ring = Hex::new(2, -5).ring(3);
+        assert_eq!(ring.len(), 18);
+        assert!(ring.iter().all(|h| h.distance(Hex::new(2, -5)) == 3));
+        assert_eq!(origin.spiral(2).len(), 19);
+
+        let line = origin.line_to(Hex::new(4, -2));
+        assert_eq!(line.len(), 5);
+        assert_eq!(line.first(), Some(&origin));
+        assert_eq!(line.last(), Some(&Hex::new(4, -2)));
+        assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+    }
+}
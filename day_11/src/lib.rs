@@ -0,0 +1,4 @@
+//! Hex grid utilities, shared by the day 11 solver and anything else
+//! that needs to walk a hex grid.
+
+pub mod hex;
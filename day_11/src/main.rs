@@ -1,7 +1,8 @@
-use std::collections::{BinaryHeap, HashSet};
-use std::str::FromStr;
-use std::cmp::Ordering;
+extern crate day_11;
 
+use std::env;
+
+use day_11::hex::{self, Direction, Hex};
 
 fn main() {
     let input = include_str!("../input.txt").trim()
@@ -9,143 +10,36 @@ fn main() {
         .map(|e| e.parse::<Direction>().unwrap())
         .collect::<Vec<_>>();
 
-    let path = build_path(&input);
-    let p1 = shortest_path(path[0].position, path.last().unwrap().position);
-    println!("part one: {}", p1);
-
-    let p2 = furthest_point(&path);
-    println!("part one: {}", p2);
-}
-
-type Position = (isize, isize);
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Node {
-    position: Position,
-    edges: [Position; 6],
-    cost: usize,
-}
-
-fn build_path(directions: &[Direction]) -> Vec<Node> {
-    // start with a node at (0, 0)
-    // track nodes in a map
-    // at each step, create the node if needed
-    // stop with a big list of nodes?
-    let mut nodes = Vec::new();
-    let mut cur_pos = (0, 0);
-    let mut cur_node = Node::new(cur_pos, 0);
-    nodes.push(cur_node);
-
-    for direction in directions {
-        cur_pos = cur_node.relative_position(*direction);
-        cur_node = Node::new(cur_pos, 0);
-        nodes.push(cur_node);
-    }
-    nodes
-}
-
-fn shortest_path(pos: Position, target: Position) -> usize {
-    let mut heap = BinaryHeap::new();
-    let mut seen = HashSet::new();
-    heap.push(Node::new(pos, 0));
-    while let Some(next) = heap.pop() {
-        //println!("visiting {:?}", next);
-        if next.position.0 > 1000 { break }
-        if seen.contains(&next.position) { continue }
-        if next.position == target { return next.cost }
-        seen.insert(next.position);
-        let cur_dist = position_distance(next.position, target);
-        let to_visit = next.edges.iter()
-            .filter(|e| !seen.contains(e))
-            .filter(|e| position_distance(**e, target) <= cur_dist)
-            .map(|e| Node::new(*e, next.cost + 1));
-        heap.extend(to_visit);
-    }
-    unreachable!()
-}
-
-fn furthest_point(path: &[Node]) -> usize {
-    let start = path.first().unwrap();
-    let mut max_dist = 0;
-    for node in path {
-        let dist = node.distance_from(start);
-        max_dist = max_dist.max(dist);
-    }
-
-    max_dist
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    North,
-    NorthEast,
-    SouthEast,
-    South,
-    SouthWest,
-    NorthWest,
-}
-
-impl Ord for Node {
-    fn cmp(&self, other: &Node) -> Ordering {
-        other.cost.cmp(&self.cost)
-            .then_with(|| self.position.cmp(&other.position))
-    }
-}
-
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        other.cost.partial_cmp(&self.cost)
-    }
-}
-
-impl Node {
-    fn new(position: Position, cost: usize) -> Self {
-        let edges = [
-            (position.0, position.1 + 2),
-            (position.0 + 1, position.1 + 1),
-            (position.0 + 1, position.1 - 1),
-            (position.0, position.1 - 2),
-            (position.0 - 1, position.1 - 1),
-            (position.0 - 1, position.1 + 1),
-        ];
-
-        Node { position, edges, cost }
-    }
-
-    fn relative_position(&self, direction: Direction) -> Position {
-        self.edges[direction as usize]
-    }
-
-    fn distance_from(&self, other: &Node) -> usize {
-        position_distance(self.position, other.position)
+    match env::args().nth(1).as_deref() {
+        Some("--simplify") => {
+            let end = input.iter().fold(Hex::default(), |pos, dir| pos.neighbour(*dir));
+            println!("{}", hex::format_path(&Hex::default().path_to(end)));
+            return
+        }
+        Some("--profile") => {
+            for dist in hex::distance_profile(Hex::default(), &input) {
+                println!("{}", dist);
+            }
+            return
+        }
+        _ => (),
     }
-}
 
-fn position_distance(p1: Position, p2: Position) -> usize {
-    let mut norm_x = (p1.0 - p2.0).abs() as usize;
-    let mut norm_y = (p1.1 - p2.1).abs() as usize;
-    let diag = norm_y.min(norm_x);
-    norm_x -= diag;
-    norm_y -= diag;
-    if norm_x == norm_y { return diag }
-    if norm_x == 0 { return diag + norm_y / 2 }
-    if norm_y == 0 { return diag + norm_x }
-    unreachable!()
+    let (p1, p2) = part_both(&input);
+    println!("part one: {}", p1);
+    println!("part two: {}", p2);
 }
 
-impl FromStr for Direction {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "n" => Ok(Direction::North),
-            "ne" => Ok(Direction::NorthEast),
-            "nw" => Ok(Direction::NorthWest),
-            "s" => Ok(Direction::South),
-            "se" => Ok(Direction::SouthEast),
-            "sw" => Ok(Direction::SouthWest),
-            other => Err(format!("unexpected direction {}", other)),
-        }
+/// Walks the path once, returning the final distance from the origin
+/// and the furthest distance reached along the way.
+fn part_both(directions: &[Direction]) -> (usize, usize) {
+    let mut pos = Hex::default();
+    let mut furthest = 0;
+    for dir in directions {
+        pos = pos.neighbour(*dir);
+        furthest = furthest.max(pos.length());
     }
+    (pos.length(), furthest)
 }
 
 #[cfg(test)]
@@ -153,10 +47,27 @@ mod tests {
     use super::*;
     #[test]
     fn distance() {
-        assert_eq!(position_distance((0, 0), (0, 2)), 1);
-        assert_eq!(position_distance((0, 0), (0, -8)), 4);
-        assert_eq!(position_distance((0, 0), (-6, 0)), 6);
-        assert_eq!(position_distance((0, 0), (-3, -1)), 3);
-        assert_eq!(position_distance((0, 0), (3, 7)), 5);
+        let doubled = |x, y| Hex::from_doubled(x, y).length();
+        assert_eq!(doubled(0, 2), 1);
+        assert_eq!(doubled(0, -8), 4);
+        assert_eq!(doubled(-6, 0), 6);
+        assert_eq!(doubled(-3, -1), 3);
+        assert_eq!(doubled(3, 7), 5);
+    }
+
+    #[test]
+    fn test_part_both() {
+        let parse = |s: &str| s.split(',').map(|d| d.parse::<Direction>().unwrap()).collect::<Vec<_>>();
+        assert_eq!(part_both(&parse("ne,ne,ne")), (3, 3));
+        assert_eq!(part_both(&parse("ne,ne,sw,sw")), (0, 2));
+        assert_eq!(part_both(&parse("ne,ne,s,s")), (2, 2));
+        assert_eq!(part_both(&parse("se,sw,se,sw,sw")), (3, 3));
+
+        // well past where the old search gave up
+        let mut long = vec![Direction::NorthEast; 1500];
+        long.extend(vec![Direction::South; 700]);
+        assert_eq!(part_both(&long), (1500, 1500));
+        long.extend(vec![Direction::SouthWest; 2000]);
+        assert_eq!(part_both(&long), (1200, 1500));
     }
 }
@@ -0,0 +1,231 @@
+//! A disjoint-set forest for grouping programs that can talk to each other.
+
+use std::collections::HashMap;
+
+/// Tracks which programs are connected, merging sets by size so that
+/// `find` stays close to constant time.
+///
+/// Programs are known by their ids, which needn't be contiguous; only ids
+/// that have been added take up space.
+#[derive(Debug, Clone, Default)]
+pub struct DisjointSet {
+    /// The id of each element.
+    ids: Vec<usize>,
+    /// The element for each id.
+    index: HashMap<usize, usize>,
+    parent: Vec<usize>,
+    /// For each root, the number of elements in its set.
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl DisjointSet {
+    /// Creates singleton sets for ids `0..len`.
+    pub fn new(len: usize) -> Self {
+        let mut sets = DisjointSet::default();
+        for id in 0..len {
+            sets.insert(id);
+        }
+        sets
+    }
+
+    /// Builds the sets from a pipe list, where each line looks like
+    /// `2 <-> 0, 3, 4`. Every program with a line or a mention is included.
+    pub fn from_pipes(input: &str) -> Result<Self, String> {
+        let mut sets = DisjointSet::default();
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() { continue }
+            let (id, neighbours) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            sets.insert(id);
+            for n in neighbours {
+                sets.union(id, n);
+            }
+        }
+        Ok(sets)
+    }
+
+    /// Adds `id` as a singleton set. Returns `false` if it was already
+    /// present.
+    pub fn insert(&mut self, id: usize) -> bool {
+        if self.index.contains_key(&id) {
+            return false
+        }
+        let x = self.ids.len();
+        self.index.insert(id, x);
+        self.ids.push(id);
+        self.parent.push(x);
+        self.size.push(1);
+        self.count += 1;
+        true
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    /// The number of programs.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn element(&self, id: usize) -> usize {
+        match self.index.get(&id) {
+            Some(&x) => x,
+            None => panic!("no program {}", id),
+        }
+    }
+
+    fn root(&self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Like `root`, but shortens the path as it goes.
+    fn root_mut(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            let grandparent = self.parent[self.parent[x]];
+            self.parent[x] = grandparent;
+            x = grandparent;
+        }
+        x
+    }
+
+    /// The representative of `id`'s set.
+    pub fn find(&self, id: usize) -> usize {
+        self.ids[self.root(self.element(id))]
+    }
+
+    /// Merges the sets containing `a` and `b`, adding either if it's new.
+    /// Returns `false` if they were already connected.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        self.insert(a);
+        self.insert(b);
+        let (a, b) = (self.element(a), self.element(b));
+        let (a, b) = (self.root_mut(a), self.root_mut(b));
+        if a == b {
+            return false
+        }
+        let (big, small) = if self.size[a] >= self.size[b] { (a, b) } else { (b, a) };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        self.count -= 1;
+        true
+    }
+
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of programs in `id`'s set.
+    pub fn component_size(&self, id: usize) -> usize {
+        self.size[self.root(self.element(id))]
+    }
+
+    /// The number of disjoint sets.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The members of every set, each sorted, ordered by smallest member.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut by_root = vec![None; self.len()];
+        let mut out: Vec<Vec<usize>> = Vec::with_capacity(self.count);
+        for x in 0..self.len() {
+            let root = self.root(x);
+            let idx = match by_root[root] {
+                Some(idx) => idx,
+                None => {
+                    by_root[root] = Some(out.len());
+                    out.push(Vec::with_capacity(self.size[root]));
+                    out.len() - 1
+                }
+            };
+            out[idx].push(self.ids[x]);
+        }
+        for members in &mut out {
+            members.sort();
+        }
+        out.sort_by_key(|members| members[0]);
+        out
+    }
+
+    /// The members of `id`'s set, sorted.
+    pub fn members(&self, id: usize) -> Vec<usize> {
+        let root = self.root(self.element(id));
+        let mut members = (0..self.len())
+            .filter(|&x| self.root(x) == root)
+            .map(|x| self.ids[x])
+            .collect::<Vec<_>>();
+        members.sort();
+        members
+    }
+}
+
+/// Parses a line like `2 <-> 0, 3, 4` into the program and its neighbours.
+pub fn parse_line(line: &str) -> Result<(usize, Vec<usize>), String> {
+    let mut parts = line.splitn(2, "<->");
+    let id = parts.next().unwrap().trim();
+    let id = id.parse::<usize>().map_err(|_| format!("bad program id '{}'", id))?;
+    let rest = parts.next().ok_or_else(|| format!("missing '<->' after {}", id))?;
+    let neighbours = rest.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().map_err(|_| format!("bad program id '{}'", s)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((id, neighbours))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &str = "0 <-> 2
+1 <-> 1
+2 <-> 0, 3, 4
+3 <-> 2, 4
+4 <-> 2, 3, 6
+5 <-> 6
+6 <-> 4, 5";
+
+    #[test]
+    fn example() {
+        let sets = DisjointSet::from_pipes(EXAMPLE).unwrap();
+        assert_eq!(sets.component_size(0), 6);
+        assert_eq!(sets.component_size(1), 1);
+        assert_eq!(sets.count(), 2);
+        assert!(sets.connected(0, 5));
+        assert!(!sets.connected(1, 5));
+        assert_eq!(sets.components(), vec![vec![0, 2, 3, 4, 5, 6], vec![1]]);
+        assert_eq!(sets.members(6), vec![0, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_line("2 <-> 0, 3, 4"), Ok((2, vec![0, 3, 4])));
+        assert!(parse_line("2 <- 0").is_err());
+        assert!(DisjointSet::from_pipes("0 <-> 1\n1 <-> x").unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn sparse_ids() {
+        let sets = DisjointSet::from_pipes("10 <-> 20\n20 <-> 10\n30 <-> 30").unwrap();
+        assert_eq!(sets.count(), 2);
+        assert_eq!(sets.len(), 3);
+        assert_eq!(sets.components(), vec![vec![10, 20], vec![30]]);
+        assert!(!sets.contains(0));
+
+        let sets = DisjointSet::from_pipes("0 <-> 5\n5 <-> 0").unwrap();
+        assert_eq!(sets.count(), 1);
+
+        // mentioned without a line of its own still counts
+        let sets = DisjointSet::from_pipes("4000000000 <-> 0").unwrap();
+        assert_eq!((sets.len(), sets.count()), (2, 1));
+        assert_eq!(sets.members(0), vec![0, 4000000000]);
+    }
+}
@@ -0,0 +1,301 @@
+//! A pipe graph that can be edited in place, plus input validation.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use components::{parse_line, DisjointSet};
+
+/// Something suspicious in a pipe list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// `from` lists `to`, but `to`'s own line doesn't list `from`.
+    Asymmetric { from: usize, to: usize, to_line: usize },
+    /// `from` lists `to`, which has no line of its own.
+    Dangling { from: usize, to: usize },
+    /// `id` has more than one line; the first is at `first_line`.
+    Duplicate { id: usize, first_line: usize },
+}
+
+/// A `Problem`, with the (1-based) line it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match self.problem {
+            Problem::Asymmetric { from, to, to_line } =>
+                write!(f, "{} lists {}, but {} (line {}) doesn't list {}", from, to, to, to_line, from),
+            Problem::Dangling { from, to } =>
+                write!(f, "{} lists {}, which has no line of its own", from, to),
+            Problem::Duplicate { id, first_line } =>
+                write!(f, "{} was already described on line {}", id, first_line),
+        }
+    }
+}
+
+/// Checks that every pipe in `input` is listed from both ends, and that
+/// every program mentioned has a line of its own.
+///
+/// Lines that don't parse are reported as an `Err`, since nothing else
+/// can be said about them.
+pub fn validate(input: &str) -> Result<Vec<Diagnostic>, String> {
+    let mut lines: BTreeMap<usize, (usize, Vec<usize>)> = BTreeMap::new();
+    let mut diagnostics = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() { continue }
+        let (id, neighbours) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if let Some(&(first_line, _)) = lines.get(&id) {
+            diagnostics.push(Diagnostic { line: i + 1, problem: Problem::Duplicate { id, first_line } });
+            continue
+        }
+        lines.insert(id, (i + 1, neighbours));
+    }
+
+    for (&from, &(line, ref neighbours)) in &lines {
+        for &to in neighbours {
+            let problem = match lines.get(&to) {
+                None => Problem::Dangling { from, to },
+                Some(&(to_line, ref theirs)) if !theirs.contains(&from) =>
+                    Problem::Asymmetric { from, to, to_line },
+                Some(_) => continue,
+            };
+            diagnostics.push(Diagnostic { line, problem });
+        }
+    }
+    diagnostics.sort_by_key(|d| d.line);
+    Ok(diagnostics)
+}
+
+/// An undirected pipe graph whose components are kept up to date as
+/// pipes are added and removed.
+///
+/// Adding a pipe is a single union. Removing one searches outward from
+/// both ends at once, stopping as soon as they meet; only if the pipe was
+/// a bridge are the components rebuilt.
+#[derive(Debug, Clone, Default)]
+pub struct PipeGraph {
+    adjacent: BTreeMap<usize, BTreeSet<usize>>,
+    sets: DisjointSet,
+}
+
+impl PipeGraph {
+    pub fn new() -> Self {
+        PipeGraph::default()
+    }
+
+    /// Builds a graph from a pipe list. Each pipe only needs to be listed
+    /// from one end; use `validate` to check that the input is consistent.
+    pub fn from_pipes(input: &str) -> Result<Self, String> {
+        let mut graph = PipeGraph::new();
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() { continue }
+            let (id, neighbours) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            graph.add_program(id);
+            for n in neighbours {
+                graph.add_pipe(id, n);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// The number of programs, including any that were only mentioned.
+    pub fn len(&self) -> usize {
+        self.adjacent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adjacent.is_empty()
+    }
+
+    /// Adds program `id`, unconnected, if it doesn't exist yet.
+    pub fn add_program(&mut self, id: usize) {
+        self.adjacent.entry(id).or_default();
+        self.sets.insert(id);
+    }
+
+    pub fn neighbours(&self, id: usize) -> &BTreeSet<usize> {
+        &self.adjacent[&id]
+    }
+
+    pub fn has_pipe(&self, a: usize, b: usize) -> bool {
+        self.adjacent.get(&a).is_some_and(|n| n.contains(&b))
+    }
+
+    /// Connects `a` and `b`. Returns `false` if they were already piped.
+    pub fn add_pipe(&mut self, a: usize, b: usize) -> bool {
+        self.add_program(a);
+        self.add_program(b);
+        if !self.adjacent.get_mut(&a).unwrap().insert(b) {
+            return false
+        }
+        self.adjacent.get_mut(&b).unwrap().insert(a);
+        self.sets.union(a, b);
+        true
+    }
+
+    /// Disconnects `a` and `b`. Returns `false` if there was no such pipe.
+    pub fn remove_pipe(&mut self, a: usize, b: usize) -> bool {
+        if !self.has_pipe(a, b) {
+            return false
+        }
+        self.adjacent.get_mut(&a).unwrap().remove(&b);
+        self.adjacent.get_mut(&b).unwrap().remove(&a);
+        if !self.reachable(a, b) {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// Searches from `a` and `b` in lockstep, so a pipe inside a cycle is
+    /// confirmed without walking the whole component.
+    fn reachable(&self, a: usize, b: usize) -> bool {
+        if a == b {
+            return true
+        }
+        // 1 if reached from a, 2 if reached from b
+        let mut seen = BTreeMap::new();
+        let mut queues = [VecDeque::new(), VecDeque::new()];
+        seen.insert(a, 1);
+        seen.insert(b, 2);
+        queues[0].push_back(a);
+        queues[1].push_back(b);
+        while !queues[0].is_empty() && !queues[1].is_empty() {
+            for &(side, mark) in &[(0, 1), (1, 2)] {
+                let node = match queues[side].pop_front() {
+                    Some(node) => node,
+                    None => return false,
+                };
+                for &next in &self.adjacent[&node] {
+                    match seen.get(&next).cloned() {
+                        Some(m) if m != mark => return true,
+                        Some(_) => (),
+                        None => {
+                            seen.insert(next, mark);
+                            queues[side].push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn rebuild(&mut self) {
+        self.sets = DisjointSet::default();
+        for (&a, neighbours) in &self.adjacent {
+            self.sets.insert(a);
+            for &b in neighbours.range(a..) {
+                self.sets.union(a, b);
+            }
+        }
+    }
+
+    /// The current components.
+    pub fn components(&self) -> &DisjointSet {
+        &self.sets
+    }
+
+    /// The graph in Graphviz DOT format. Each pipe appears once; programs
+    /// are grouped into a cluster per component.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("graph pipes {\n");
+        for (i, members) in self.sets.components().iter().enumerate() {
+            out.push_str(&format!("    subgraph cluster_{} {{\n", i));
+            for &a in members {
+                out.push_str(&format!("        {};\n", a));
+                for &b in self.adjacent[&a].range(a..) {
+                    out.push_str(&format!("        {} -- {};\n", a, b));
+                }
+            }
+            out.push_str("    }\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &str = "0 <-> 2
+1 <-> 1
+2 <-> 0, 3, 4
+3 <-> 2, 4
+4 <-> 2, 3, 6
+5 <-> 6
+6 <-> 4, 5";
+
+    #[test]
+    fn validation() {
+        assert_eq!(validate(EXAMPLE), Ok(Vec::new()));
+        let bad = "0 <-> 2\n\n2 <-> 3\n3 <-> 2, 9\n3 <-> 2";
+        let found = validate(bad).unwrap();
+        assert_eq!(found, vec![
+            Diagnostic { line: 1, problem: Problem::Asymmetric { from: 0, to: 2, to_line: 3 } },
+            Diagnostic { line: 4, problem: Problem::Dangling { from: 3, to: 9 } },
+            Diagnostic { line: 5, problem: Problem::Duplicate { id: 3, first_line: 4 } },
+        ]);
+        assert_eq!(found[0].to_string(), "line 1: 0 lists 2, but 2 (line 3) doesn't list 0");
+        assert!(validate("0 <-> 1\n1 -> 0").unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn incremental() {
+        let mut graph = PipeGraph::from_pipes(EXAMPLE).unwrap();
+        assert_eq!(graph.components().count(), 2);
+
+        // 2-3-4 is a cycle, so this doesn't split anything
+        assert!(graph.remove_pipe(3, 2));
+        assert!(!graph.remove_pipe(2, 3));
+        assert_eq!(graph.components().component_size(0), 6);
+
+        // but 4-6 is a bridge
+        assert!(graph.remove_pipe(6, 4));
+        assert_eq!(graph.components().count(), 3);
+        assert_eq!(graph.components().members(6), vec![5, 6]);
+
+        assert!(graph.add_pipe(1, 5));
+        assert!(!graph.add_pipe(5, 1));
+        assert!(graph.components().connected(1, 6));
+
+        graph.add_pipe(8, 0);
+        assert_eq!(graph.len(), 8);
+        // 7 was never mentioned, so it doesn't exist
+        assert_eq!(graph.components().count(), 2);
+        assert_eq!(graph.components().component_size(8), 5);
+        assert!(!graph.components().contains(7));
+    }
+
+    #[test]
+    fn sparse_ids() {
+        let mut graph = PipeGraph::from_pipes("10 <-> 20\n20 <-> 10\n30 <-> 30").unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.components().count(), 2);
+        graph.add_program(1_000_000);
+        assert_eq!(graph.components().count(), 3);
+        assert!(graph.remove_pipe(20, 10));
+        assert_eq!(graph.components().components(), vec![vec![10], vec![20], vec![30], vec![1_000_000]]);
+    }
+
+    #[test]
+    fn dot() {
+        let graph = PipeGraph::from_pipes("0 <-> 1\n1 <-> 0\n2 <-> 2").unwrap();
+        assert_eq!(graph.to_dot(), "graph pipes {
+    subgraph cluster_0 {
+        0;
+        0 -- 1;
+        1;
+    }
+    subgraph cluster_1 {
+        2;
+        2 -- 2;
+    }
+}
+");
+    }
+}
@@ -0,0 +1,4 @@
+//! Connectivity queries over the village's pipe network.
+
+pub mod components;
+pub mod graph;
@@ -0,0 +1,209 @@
+//! Solving the firewall from scanner periods, without simulating it.
+//!
+//! A scanner with range `r` bounces back and forth, so it's at the top of
+//! its layer exactly when `t % 2(r - 1) == 0`. A packet sent after `delay`
+//! reaches depth `d` at `t = delay + d`, so each layer rules out a single
+//! residue of `delay` modulo its period.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Past this many candidate residues the sieve stops combining periods,
+/// and the remaining layers are checked against each candidate instead.
+const SIEVE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scanner {
+    pub depth: usize,
+    pub range: usize,
+}
+
+impl Scanner {
+    pub fn new(depth: usize, range: usize) -> Self {
+        Scanner { depth, range }
+    }
+
+    /// How often the scanner returns to the top. A scanner of range 1
+    /// never leaves it.
+    pub fn period(&self) -> usize {
+        if self.range <= 1 { 1 } else { 2 * (self.range - 1) }
+    }
+
+    /// Whether a packet sent after `delay` is caught here. A layer with
+    /// range 0 has no scanner, and never catches anything.
+    pub fn catches(&self, delay: usize) -> bool {
+        self.range > 0 && (delay + self.depth).is_multiple_of(self.period())
+    }
+
+    pub fn severity(&self) -> usize {
+        self.depth * self.range
+    }
+}
+
+/// A layer where the packet was caught.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Catch {
+    /// Picoseconds since the first packet could have been sent.
+    pub time: usize,
+    pub depth: usize,
+    pub range: usize,
+    pub severity: usize,
+}
+
+/// Every layer that catches a packet sent after `delay`, in the order
+/// they're reached.
+pub fn trip_report(scanners: &[Scanner], delay: usize) -> Vec<Catch> {
+    let mut report = scanners.iter()
+        .filter(|s| s.catches(delay))
+        .map(|s| Catch {
+            time: delay + s.depth,
+            depth: s.depth,
+            range: s.range,
+            severity: s.severity(),
+        })
+        .collect::<Vec<_>>();
+    report.sort_by_key(|c| c.depth);
+    report
+}
+
+/// The total severity of a trip that starts after `delay`.
+pub fn severity(scanners: &[Scanner], delay: usize) -> usize {
+    scanners.iter()
+        .filter(|s| s.catches(delay))
+        .map(Scanner::severity)
+        .sum()
+}
+
+/// The smallest delay that gets through without being caught, or `None`
+/// if every delay is caught.
+///
+/// Layers that share a period are merged into one sparse set of forbidden
+/// residues, so a scanner with a huge range costs no more than a small
+/// one. Periods are then combined, smallest first, into the residues
+/// modulo their lcm that no layer forbids. If that set grows too large,
+/// the leftover periods are checked one candidate at a time.
+pub fn min_safe_delay(scanners: &[Scanner]) -> Option<usize> {
+    let mut forbidden: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for s in scanners.iter().filter(|s| s.range > 0) {
+        let p = s.period();
+        forbidden.entry(p).or_default().insert((p - s.depth % p) % p);
+    }
+    if forbidden.iter().any(|(&p, bad)| bad.len() == p) {
+        return None
+    }
+
+    // the whole pattern repeats after this many steps, if it fits
+    let full_period = forbidden.keys().try_fold(1usize, |acc, &p| checked_lcm(acc, p));
+
+    let mut modulus = 1;
+    let mut allowed = vec![0];
+    let mut periods = forbidden.into_iter().peekable();
+    while let Some(&(p, _)) = periods.peek() {
+        let next = match checked_lcm(modulus, p) {
+            Some(next) if allowed.len().checked_mul(next / modulus)
+                .is_some_and(|n| n <= SIEVE_LIMIT) => next,
+            _ => break,
+        };
+        let (_, bad) = periods.next().unwrap();
+        allowed = (0..next / modulus)
+            .flat_map(|k| allowed.iter().map(move |&r| r + k * modulus))
+            .filter(|&d| !bad.contains(&(d % p)))
+            .collect();
+        modulus = next;
+        if allowed.is_empty() {
+            return None
+        }
+    }
+    allowed.sort();
+
+    let rest = periods.collect::<Vec<_>>();
+    let bound = full_period.unwrap_or(usize::MAX);
+    let mut base = 0usize;
+    while base < bound {
+        for &r in &allowed {
+            let delay = base + r;
+            if rest.iter().all(|&(p, ref bad)| !bad.contains(&(delay % p))) {
+                return Some(delay)
+            }
+        }
+        base = base.checked_add(modulus)?;
+    }
+    None
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn checked_lcm(a: usize, b: usize) -> Option<usize> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_caught(scanners: &[Scanner], delay: usize) -> bool {
+        scanners.iter().any(|s| s.catches(delay))
+    }
+
+    fn example() -> Vec<Scanner> {
+        vec![Scanner::new(0, 3), Scanner::new(1, 2), Scanner::new(4, 4), Scanner::new(6, 4)]
+    }
+
+    #[test]
+    fn example_answers() {
+        let scanners = example();
+        assert_eq!(severity(&scanners, 0), 24);
+        assert!(is_caught(&scanners, 0));
+        assert_eq!(severity(&scanners, 10), 0);
+        assert_eq!(min_safe_delay(&scanners), Some(10));
+    }
+
+    #[test]
+    fn report() {
+        let scanners = example();
+        assert_eq!(trip_report(&scanners, 0), vec![
+            Catch { time: 0, depth: 0, range: 3, severity: 0 },
+            Catch { time: 6, depth: 6, range: 4, severity: 24 },
+        ]);
+        assert_eq!(trip_report(&scanners, 4).iter().map(|c| c.time).collect::<Vec<_>>(), vec![4]);
+        assert!(trip_report(&scanners, 10).is_empty());
+    }
+
+    #[test]
+    fn impossible() {
+        assert_eq!(min_safe_delay(&[Scanner::new(3, 1)]), None);
+        // period 2 forbids odd delays, period 4 forbids 0 mod 4 and 2 mod 4
+        let scanners = [Scanner::new(1, 2), Scanner::new(0, 3), Scanner::new(2, 3)];
+        assert_eq!(min_safe_delay(&scanners), None);
+        assert_eq!(min_safe_delay(&[]), Some(0));
+    }
+
+    #[test]
+    fn huge_ranges() {
+        assert_eq!(min_safe_delay(&[Scanner::new(0, 1 << 40)]), Some(1));
+        // the big periods are too much for the sieve, so they're checked
+        // per candidate
+        let scanners = [
+            Scanner::new(0, 1 << 40),
+            Scanner::new(1, 1 << 41),
+            Scanner::new(1, 2),
+            Scanner::new(4, 3),
+        ];
+        let slow = (0..).find(|&d| !is_caught(&scanners, d)).unwrap();
+        assert_eq!(min_safe_delay(&scanners), Some(slow));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        // enough layers that the sieve has to give up and scan, all of
+        // which let through a packet sent after 123456
+        let scanners = (0..300)
+            .map(|d| Scanner::new(d, 2 + (d * 7 + d / 3) % 19))
+            .filter(|s| !s.catches(123_456))
+            .collect::<Vec<_>>();
+        let fast = min_safe_delay(&scanners).unwrap();
+        let slow = (0..).find(|&d| !is_caught(&scanners, d)).unwrap();
+        assert_eq!(fast, slow);
+    }
+}
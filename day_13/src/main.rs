@@ -1,16 +1,73 @@
+use std::env;
+
+mod firewall;
+
+use firewall::Scanner;
+
 fn main() {
     let input = include_str!("../input.txt").trim()
         .lines()
         .map(parse)
         .collect::<Vec<_>>();
 
-    let p1 = part_one(&input);
+    let scanners = input.iter()
+        .map(|&(depth, range)| Scanner::new(depth, range))
+        .collect::<Vec<_>>();
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let delay = args.get(1).map(|d| d.parse::<usize>().expect("delay should be a number"));
+    match args.first().map(String::as_str) {
+        Some("--simulate") => {
+            println!("part one: {}", part_one(&input));
+            println!("part two: {}", part_two(&input));
+            return
+        }
+        Some("--report") => {
+            print_report(&scanners, delay.unwrap_or(0));
+            return
+        }
+        Some("--animate") => {
+            for frame in animate(&input, delay.unwrap_or(0)) {
+                println!("{}\n", frame);
+            }
+            return
+        }
+        _ => (),
+    }
+
+    let p1 = firewall::severity(&scanners, 0);
     println!("part one: {}", p1);
 
-    let p2 = part_two(&input);
+    let p2 = firewall::min_safe_delay(&scanners).expect("no safe delay");
     println!("part two: {}", p2);
 }
 
+fn print_report(scanners: &[Scanner], delay: usize) {
+    let report = firewall::trip_report(scanners, delay);
+    println!("{:>8} {:>5} {:>5} {:>8}", "time", "depth", "range", "severity");
+    for catch in &report {
+        println!("{:>8} {:>5} {:>5} {:>8}", catch.time, catch.depth, catch.range, catch.severity);
+    }
+    let total: usize = report.iter().map(|c| c.severity).sum();
+    println!("caught {} time(s), total severity {}", report.len(), total);
+}
+
+/// One frame per picosecond that the packet spends in the firewall, in
+/// the style of the puzzle's illustrations.
+fn animate(input: &[(usize, usize)], delay: usize) -> Vec<String> {
+    let mut board = Board::new(input);
+    for _ in 0..delay {
+        board.step();
+    }
+    (0..board.steps.len())
+        .map(|pos| {
+            let frame = format!("Picosecond {}:\n{}", delay + pos, board.render(Some(pos)));
+            board.step();
+            frame
+        })
+        .collect()
+}
+
 fn parse(line: &str) -> (usize, usize) {
     let mut iter = line.split_whitespace()
         .map(|el| str::parse::<usize>(el.trim_matches(':')).unwrap());
@@ -29,7 +86,7 @@ impl Board {
             while idx > steps.len() {
                 steps.push(None)
             }
-            steps.push(Some(Layer { depth: idx, range: range, scanner_pos: 0, advancing: true }))
+            steps.push(Some(Layer { depth: idx, range, scanner_pos: 0, advancing: true }))
         }
         Board { steps }
     }
@@ -42,15 +99,42 @@ impl Board {
     }
 
     fn seen_at_pos(&self, pos: usize) -> bool {
-        match self.steps[pos] {
-            Some(ref layer) if layer.scanner_pos == 0 => true,
-            _ => false
+        matches!(self.steps[pos], Some(ref layer) if layer.scanner_pos == 0)
+    }
+
+    /// Draws the layers with each scanner as an `S`, and the packet (if
+    /// any) as parentheses around the top of its layer.
+    fn render(&self, packet: Option<usize>) -> String {
+        let depth = self.steps.iter().flatten().map(|l| l.range).max().unwrap_or(0);
+        let mut lines = vec![(0..self.steps.len())
+            .map(|pos| format!("{:^3}", pos))
+            .collect::<Vec<_>>()
+            .join(" ")];
+        for row in 0..depth.max(1) {
+            let line = self.steps.iter().enumerate()
+                .map(|(pos, layer)| {
+                    let (open, close) = if packet == Some(pos) && row == 0 { ('(', ')') } else { ('[', ']') };
+                    match *layer {
+                        Some(ref l) if row < l.range => {
+                            let fill = if l.scanner_pos == row { 'S' } else { ' ' };
+                            format!("{}{}{}", open, fill, close)
+                        }
+                        None if row == 0 && open == '(' => "(.)".to_owned(),
+                        _ if row == 0 => "...".to_owned(),
+                        _ => "   ".to_owned(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(line);
         }
+        lines.iter().map(|l| l.trim_end()).collect::<Vec<_>>().join("\n")
     }
 
     fn step(&mut self) {
         self.steps.iter_mut()
-            .for_each(|l| { l.as_mut().map(|l| l.step()); } )
+            .flatten()
+            .for_each(Layer::step)
     }
 }
 
@@ -64,6 +148,9 @@ struct Layer {
 
 impl Layer {
     fn step(&mut self) {
+        if self.range <= 1 {
+            return
+        }
         let new_pos = if self.advancing { self.scanner_pos + 1 } else { self.scanner_pos - 1 };
         if new_pos == 0 || new_pos == self.range -1 { self.advancing = !self.advancing }
         self.scanner_pos = new_pos;
@@ -85,26 +172,32 @@ fn part_one(input: &[(usize, usize)]) -> usize {
     score
 }
 
-/// this is... bad
+/// Sends a packet through `board` from its current state, returning the
+/// position where it's first caught, or the board's length if it makes it
+/// through.
+fn steps_until_hit(board: &mut Board) -> usize {
+    let mut cur_pos = 0;
+    while cur_pos < board.steps.len() {
+        if board.seen_at_pos(cur_pos) {
+            break
+        }
+        board.step();
+        cur_pos += 1;
+    }
+    cur_pos
+}
+
+/// this is... bad; see `firewall::min_safe_delay` for the fast version.
 fn part_two(input: &[(usize, usize)]) -> usize {
     let mut big_board = Board::new(input);
     let mut offset_time = 0;
-    'outer: loop {
-        big_board.step();
-        offset_time += 1;
+    loop {
         let mut board = big_board.clone();
-        let mut cur_pos = 0;
-        while cur_pos < board.steps.len() {
-            if board.seen_at_pos(cur_pos) {
-                //if cur_pos > 30 {
-                    //println!("offset {} seen at {}", offset_time, cur_pos);
-                //}
-                continue 'outer
-            }
-            board.step();
-            cur_pos += 1;
+        if steps_until_hit(&mut board) == board.steps.len() {
+            return offset_time
         }
-        return offset_time
+        big_board.step();
+        offset_time += 1;
     }
 }
 
@@ -128,6 +221,25 @@ mod tests {
         assert_eq!(r, 10);
     }
 
+    #[test]
+    fn render() {
+        let inp = vec![(0, 3), (1, 2), (4, 4), (6, 4)];
+        let frames = animate(&inp, 0);
+        assert_eq!(frames.len(), 7);
+        assert_eq!(frames[0], "Picosecond 0:
+ 0   1   2   3   4   5   6
+(S) [S] ... ... [S] ... [S]
+[ ] [ ]         [ ]     [ ]
+[ ]             [ ]     [ ]
+                [ ]     [ ]");
+        assert_eq!(frames[2], "Picosecond 2:
+ 0   1   2   3   4   5   6
+[ ] [S] (.) ... [ ] ... [ ]
+[ ] [ ]         [ ]     [ ]
+[S]             [S]     [S]
+                [ ]     [ ]");
+    }
+
     #[test]
     fn hit_at() {
         let inp = vec![(0, 3), (1, 2), (4, 4), (6, 4)];
@@ -0,0 +1,258 @@
+//! A packed grid of bits, and labelling of its connected regions.
+
+/// A `width` × `height` grid of bits, stored row-major, 64 to a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words: Vec<u64>,
+}
+
+/// Which cells count as touching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only cells sharing an edge.
+    Four,
+    /// Cells sharing an edge or a corner.
+    Eight,
+}
+
+/// The smallest rectangle containing a region, inclusive on all sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// The number of cells in the region.
+    pub size: usize,
+    pub bounds: Bounds,
+}
+
+/// The result of labelling a grid: which region each set cell belongs to.
+#[derive(Debug, Clone)]
+pub struct Labels {
+    width: usize,
+    height: usize,
+    /// `0` for unset cells, otherwise one more than the region index.
+    cells: Vec<u32>,
+    regions: Vec<Region>,
+}
+
+impl BitGrid {
+    /// An all-clear grid.
+    pub fn new(width: usize, height: usize) -> Self {
+        BitGrid { width, height, words: vec![0; (width * height).div_ceil(64)] }
+    }
+
+    /// A grid where each cell is set if `f(x, y)` is true.
+    pub fn from_fn<F>(width: usize, height: usize, mut f: F) -> Self
+        where F: FnMut(usize, usize) -> bool
+    {
+        let mut grid = BitGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if f(x, y) {
+                    grid.set(x, y, true);
+                }
+            }
+        }
+        grid
+    }
+
+    /// A 128-wide grid with a row per integer, most significant bit first.
+    pub fn from_rows(rows: &[u128]) -> Self {
+        BitGrid::from_fn(128, rows.len(), |x, y| rows[y] & (1 << (127 - x)) != 0)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height,
+                "({}, {}) is outside a {}x{} grid", x, y, self.width, self.height);
+        y * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let i = self.index(x, y);
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        let i = self.index(x, y);
+        if value {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    /// The number of set cells.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// The in-bounds neighbours of `(x, y)`.
+    pub fn neighbours(&self, x: usize, y: usize, connectivity: Connectivity)
+        -> impl Iterator<Item=(usize, usize)>
+    {
+        const FOUR: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+        const EIGHT: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0),
+                                            (1, 0), (-1, 1), (0, 1), (1, 1)];
+        let offsets: &'static [(isize, isize)] = match connectivity {
+            Connectivity::Four => &FOUR,
+            Connectivity::Eight => &EIGHT,
+        };
+        let (width, height) = (self.width, self.height);
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            if nx < width && ny < height { Some((nx, ny)) } else { None }
+        })
+    }
+
+    /// Labels the connected regions of set cells, numbering them in the
+    /// order their first cell is reached scanning row by row.
+    ///
+    /// Each region is filled from an explicit stack, so large regions
+    /// can't overflow the call stack.
+    pub fn label(&self, connectivity: Connectivity) -> Labels {
+        let mut cells = vec![0u32; self.width * self.height];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.get(x, y) || cells[y * self.width + x] != 0 { continue }
+                regions.push(Region { size: 0, bounds: Bounds { min_x: x, min_y: y, max_x: x, max_y: y } });
+                let label = regions.len() as u32;
+                let region = regions.last_mut().unwrap();
+                cells[y * self.width + x] = label;
+                stack.push((x, y));
+                while let Some((cx, cy)) = stack.pop() {
+                    region.size += 1;
+                    region.bounds.include(cx, cy);
+                    for (nx, ny) in self.neighbours(cx, cy, connectivity) {
+                        let i = ny * self.width + nx;
+                        if cells[i] == 0 && self.get(nx, ny) {
+                            cells[i] = label;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+        }
+        Labels { width: self.width, height: self.height, cells, regions }
+    }
+}
+
+impl Bounds {
+    fn include(&mut self, x: usize, y: usize) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    pub fn width(&self) -> usize {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.max_y - self.min_y + 1
+    }
+}
+
+impl Labels {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The index of the region containing `(x, y)`, if the cell is set.
+    pub fn get(&self, x: usize, y: usize) -> Option<usize> {
+        assert!(x < self.width && y < self.height);
+        match self.cells[y * self.width + x] {
+            0 => None,
+            n => Some(n as usize - 1),
+        }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// The number of regions.
+    pub fn count(&self) -> usize {
+        self.regions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_art(art: &str) -> BitGrid {
+        let rows = art.lines().map(|l| l.trim().as_bytes()).collect::<Vec<_>>();
+        BitGrid::from_fn(rows[0].len(), rows.len(), |x, y| rows[y][x] == b'#')
+    }
+
+    #[test]
+    fn bits() {
+        let mut grid = BitGrid::new(130, 3);
+        grid.set(129, 2, true);
+        grid.set(0, 1, true);
+        grid.set(0, 1, false);
+        grid.set(64, 0, true);
+        assert!(grid.get(129, 2) && grid.get(64, 0));
+        assert!(!grid.get(0, 1) && !grid.get(65, 0));
+        assert_eq!(grid.count_ones(), 2);
+
+        let grid = BitGrid::from_rows(&[1 << 127 | 1, 0]);
+        assert_eq!((grid.width(), grid.height()), (128, 2));
+        assert!(grid.get(0, 0) && grid.get(127, 0) && !grid.get(1, 0));
+        assert_eq!(grid.count_ones(), 2);
+    }
+
+    #[test]
+    fn labelling() {
+        let grid = from_art("##..#
+                             ...#.
+                             #..##
+                             #####");
+        let four = grid.label(Connectivity::Four);
+        assert_eq!(four.count(), 3);
+        assert_eq!(four.get(1, 0), Some(0));
+        assert_eq!(four.get(2, 0), None);
+        assert_eq!(four.get(4, 3), Some(2));
+        let sizes = four.regions().iter().map(|r| r.size).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![2, 1, 9]);
+        assert_eq!(four.regions()[2].bounds, Bounds { min_x: 0, min_y: 1, max_x: 4, max_y: 3 });
+
+        let eight = grid.label(Connectivity::Eight);
+        assert_eq!(eight.count(), 2);
+        assert_eq!(eight.regions()[1].size, 10);
+        assert_eq!(eight.regions()[1].bounds.width(), 5);
+    }
+
+    #[test]
+    fn big_region() {
+        // a single snaking region, far too deep to fill recursively
+        let grid = BitGrid::from_fn(1000, 1000, |x, y| y % 2 == 0 || x == if y % 4 == 1 { 999 } else { 0 });
+        let labels = grid.label(Connectivity::Four);
+        assert_eq!(labels.count(), 1);
+        assert_eq!(labels.regions()[0].size, grid.count_ones());
+    }
+}
@@ -0,0 +1,136 @@
+//! Rendering grids and region labels as images, or in the terminal.
+
+use std::io::{self, Write};
+
+use grid::{BitGrid, Labels};
+
+/// The colour of free cells in colour output.
+const FREE: [u8; 3] = [24, 24, 24];
+
+/// A colour for region `index`, stepping the hue by the golden ratio so
+/// that neighbouring indices look nothing alike.
+pub fn region_colour(index: usize) -> [u8; 3] {
+    let hue = (index as f64 * 0.618_033_988_749_895).fract() * 6.0;
+    // alternate the brightness too, so there are more than a few dozen
+    // easily told apart
+    let value = if index.is_multiple_of(2) { 1.0 } else { 0.75 };
+    let saturation = 0.65;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let to_byte = |c: f64| ((c + m) * 255.0).round() as u8;
+    [to_byte(r), to_byte(g), to_byte(b)]
+}
+
+/// Writes the grid as a binary (P5) PGM, with used cells black and free
+/// cells white, each drawn as a `scale` × `scale` square.
+pub fn write_pgm<W: Write>(grid: &BitGrid, scale: usize, mut out: W) -> io::Result<()> {
+    write!(out, "P5\n{} {}\n255\n", grid.width() * scale, grid.height() * scale)?;
+    let mut row = Vec::with_capacity(grid.width() * scale);
+    for y in 0..grid.height() {
+        row.clear();
+        for x in 0..grid.width() {
+            let shade = if grid.get(x, y) { 0 } else { 255 };
+            row.extend((0..scale).map(|_| shade));
+        }
+        for _ in 0..scale {
+            out.write_all(&row)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the labels as a binary (P6) PPM, with each region in its own
+/// colour, each cell drawn as a `scale` × `scale` square.
+pub fn write_ppm<W: Write>(labels: &Labels, scale: usize, mut out: W) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", labels.width() * scale, labels.height() * scale)?;
+    let mut row = Vec::with_capacity(labels.width() * scale * 3);
+    for y in 0..labels.height() {
+        row.clear();
+        for x in 0..labels.width() {
+            let colour = labels.get(x, y).map(region_colour).unwrap_or(FREE);
+            for _ in 0..scale {
+                row.extend_from_slice(&colour);
+            }
+        }
+        for _ in 0..scale {
+            out.write_all(&row)?;
+        }
+    }
+    Ok(())
+}
+
+/// Draws the labels for a truecolour terminal, two rows of cells to a line
+/// of upper half blocks: the foreground is the top cell, and the
+/// background the bottom one.
+pub fn terminal(labels: &Labels) -> String {
+    let colour = |x, y| if y < labels.height() {
+        labels.get(x, y).map(region_colour).unwrap_or(FREE)
+    } else {
+        [0, 0, 0]
+    };
+    let mut out = String::new();
+    for y in (0..labels.height()).step_by(2) {
+        for x in 0..labels.width() {
+            let [fr, fg, fb] = colour(x, y);
+            let [br, bg, bb] = colour(x, y + 1);
+            out.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}", fr, fg, fb, br, bg, bb));
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid::Connectivity;
+
+    fn sample() -> BitGrid {
+        BitGrid::from_fn(3, 3, |x, y| x == 0 || (x == 2 && y == 2))
+    }
+
+    #[test]
+    fn pgm() {
+        let mut out = Vec::new();
+        write_pgm(&sample(), 2, &mut out).unwrap();
+        let header = b"P5\n6 6\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        let pixels = &out[header.len()..];
+        assert_eq!(pixels.len(), 36);
+        assert_eq!(&pixels[..6], &[0, 0, 255, 255, 255, 255]);
+        assert_eq!(&pixels[30..], &[0, 0, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn ppm() {
+        let labels = sample().label(Connectivity::Four);
+        let mut out = Vec::new();
+        write_ppm(&labels, 1, &mut out).unwrap();
+        let header = b"P6\n3 3\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        let pixels = out[header.len()..].chunks(3).collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 9);
+        assert_eq!(pixels[0], &region_colour(0));
+        assert_eq!(pixels[1], &FREE);
+        assert_eq!(pixels[8], &region_colour(1));
+        assert_ne!(region_colour(0), region_colour(1));
+    }
+
+    #[test]
+    fn half_blocks() {
+        let labels = sample().label(Connectivity::Four);
+        let text = terminal(&labels);
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(text.matches('\u{2580}').count(), 6);
+        assert!(text.lines().all(|l| l.ends_with("\x1b[0m")));
+    }
+}
@@ -0,0 +1,7 @@
+//! Tools for the disk grid built from knot hashes.
+
+extern crate knot_hash;
+
+pub mod grid;
+pub mod image;
+pub mod rows;
@@ -1,97 +1,80 @@
-#![feature(slice_rotate)]
+extern crate knot_hash;
+extern crate day_14;
 
-use std::fmt::Debug;
-use std::ascii::AsciiExt;
-use std::collections::HashSet;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter};
+
+use day_14::grid::{BitGrid, Connectivity};
+use day_14::image;
+use day_14::rows;
+
+/// Pixels per cell in exported images.
+const IMAGE_SCALE: usize = 4;
 
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
     let input = "hxtvlmkl";
-    let inp_vals: Vec<usize> = (0..256).collect();
+
+    match args.first().map(String::as_str) {
+        Some(flag @ "--pgm") | Some(flag @ "--ppm") => {
+            let path = args.get(1).expect("expected an output path");
+            let key = args.get(2).map(String::as_str).unwrap_or(input);
+            let grid = disk_grid(key);
+            let out = BufWriter::new(File::create(path).unwrap());
+            if flag == "--pgm" {
+                image::write_pgm(&grid, IMAGE_SCALE, out).unwrap();
+            } else {
+                image::write_ppm(&grid.label(Connectivity::Four), IMAGE_SCALE, out).unwrap();
+            }
+            return
+        }
+        Some("--batch") => {
+            batch();
+            return
+        }
+        Some("--show") => {
+            let key = args.get(1).map(String::as_str).unwrap_or(input);
+            print!("{}", image::terminal(&disk_grid(key).label(Connectivity::Four)));
+            return
+        }
+        _ => (),
+    }
 
     let p1 = part_one(input);
     println!("part one: {}", p1);
 
     // p2:
-    let grid = hash_art(&input);
-    let formatted = format_art(&grid);
     let p2 = part_two(input);
     println!("part two: {}", p2);
 }
 
-fn format_art(inp: &Vec<Vec<char>>) -> Vec<Vec<String>> {
-    let mut out = Vec::new();
-    for v in inp {
-        let format_v = v.iter()
-            .map(|c| if *c == '0' { ".".into() } else { "#".into() })
-            .collect();
-        out.push(format_v);
-    }
-    out
-}
-
-fn debug_print_grid(grid: &Vec<Vec<String>>) {
-    for line in grid {
-        println!("{}", line.iter().flat_map(|s| s.chars()).collect::<String>())
-    }
-}
-
 fn part_one(input: &str) -> usize {
-    let grid = hash_art(&input);
-    grid.iter()
-        .flat_map(|v| v.iter())
-        .map(|c| if *c == '1' { 1 } else { 0 })
-        .sum()
+    rows::used(&rows::disk_rows(input))
 }
 
 fn part_two(input: &str) -> usize {
-    let grid = hash_art(&input);
-    let mut grid = format_art(&grid);
-    let mut seen = HashSet::new();
-    let mut cur_group = 0;
-
-    for i in 0..128 {
-        for j in 0..128 {
-            if grid[i][j] == "." { continue }
-            if seen.contains(&(i, j)) { continue }
-            cur_group += 1;
-            recurs_find_neighbours(&mut grid, cur_group, (i, j), &mut seen);
-        }
-    }
-    for line in grid {
-        println!("{}", line.iter().flat_map(|s| s.chars()).collect::<String>())
-    }
-    cur_group
+    disk_grid(input).label(Connectivity::Four).count()
 }
 
-fn recurs_find_neighbours(grid: &mut Vec<Vec<String>>, cur_val: usize, cell: (usize, usize),
-                          seen: &mut HashSet<(usize, usize)>) {
-    if grid[cell.0][cell.1] == "." { return }
-    if seen.contains(&cell) { return }
-    seen.insert(cell);
-    let deb_val = cur_val % 9;
-    grid[cell.0][cell.1] = format!("{}", deb_val);
-    let neighbours = neighbours_for_cell(cell);
-    for n in neighbours {
-        recurs_find_neighbours(grid, cur_val, n, seen);
-    }
+fn disk_grid(input: &str) -> BitGrid {
+    BitGrid::from_rows(&rows::disk_rows(input))
 }
 
-fn neighbours_for_cell(cell: (usize, usize)) -> Vec<(usize, usize)> {
-    //let mut out = Vec::new();
-    let i = cell.0 as isize;
-    let j = cell.1 as isize;
-
-    let neighbours = [
-        (i - 1, j), (i + 1, j),
-        (i, j - 1), (i, j + 1),
-    ];
-
-    neighbours.iter()
-        .filter(|cell| cell.0 >= 0 && cell.1 >= 0 && cell.0 <= 127 && cell.1 <= 127)
-        .map(|cell| (cell.0 as usize, cell.1 as usize))
-        .collect()
+/// Prints used squares and regions for each key read from stdin.
+fn batch() {
+    let stdin = io::stdin();
+    let keys = stdin.lock().lines()
+        .map(|l| l.unwrap().trim().to_owned())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>();
+    for (key, disk) in keys.iter().zip(rows::disk_rows_batch(&keys)) {
+        let regions = BitGrid::from_rows(&disk).label(Connectivity::Four).count();
+        println!("{}\t{}\t{}", key, rows::used(&disk), regions);
+    }
 }
 
+#[cfg(test)]
 fn hash_art(input: &str) -> Vec<Vec<char>> {
     let mut out = Vec::new();
     for i in 0..128 {
@@ -99,83 +82,23 @@ fn hash_art(input: &str) -> Vec<Vec<char>> {
         let hash = final_knot_hash(&text_i);
         let hash = bitify_hex(&hash)
             .chars()
-            .map(|c| c)
             .collect::<Vec<_>>();
         out.push(hash);
     }
     out
 }
 
+#[cfg(test)]
 fn bitify_hex(hex_str: &str) -> String {
     hex_str.chars()
         .map(|b| format!("{:04b}", b.to_digit(16).unwrap()))
         .collect()
 }
 
-
+#[cfg(test)]
 fn final_knot_hash(input: &str) -> String {
     assert!(input.is_ascii());
-    let mut lengths: Vec<usize> = input.bytes().map(|v| v as usize).collect();
-    let mut salt = vec![17, 31, 73, 47, 23];
-    lengths.append(&mut salt);
-    let mut val: Vec<usize> = (0..256).collect();
-
-    let mut cur_pos = 0;
-    let mut skip_size = 0;
-    for _ in 0..64 {
-        let result = knot_hash(&val, &lengths, skip_size, cur_pos);
-        val = result.data;
-        cur_pos = result.final_pos;
-        skip_size = result.skip_size;
-    }
-
-    // reduce the result
-    val.chunks(16).map(|chk| chk.iter().fold(0, |ac, i| ac ^ i))
-        .inspect(|v| assert!(*v <= 255))
-        .map(to_hex)
-        .collect::<String>()
-}
-
-fn to_hex<T: Into<usize>>(i: T) -> String {
-    format!("{:02x}", i.into())
-}
-
-struct KnotResult<T> {
-    data: Vec<T>,
-    final_pos: usize,
-    skip_size: usize,
-}
-
-fn knot_hash<T: Copy + Debug>(input: &[T],
-                              lengths: &[usize],
-                              skip_size: usize,
-                              start_pos: usize) -> KnotResult<T> {
-    let mut input = input.to_owned();
-    let mut skip_size = skip_size;
-    let mut cur_pos = start_pos;
-    let input_len = input.len();
-
-    for len in lengths {
-        let start = cur_pos % input_len ;
-        let end = (cur_pos + len) % input_len;
-        if end <= start {
-            input.rotate(start);
-            {
-                let sub_v = &mut input[0..*len];
-                sub_v.reverse();
-            }
-            input.rotate(input_len - start);
-        } else {
-            assert!(end != start, "end == start, what do to?");
-            let sub_v = &mut input[start..end];
-            sub_v.reverse();
-        }
-        cur_pos += len + skip_size;
-        skip_size += 1;
-    }
-        let data = input;
-        let final_pos = cur_pos;
-        KnotResult { data, final_pos, skip_size }
+    knot_hash::to_hex(&knot_hash::hash(input.as_bytes()))
 }
 
 #[cfg(test)]
@@ -185,20 +108,31 @@ mod tests {
     fn bitify() {
         assert_eq!(bitify_hex("0"), "0000");
         assert_eq!(bitify_hex("1"), "0001");
-        assert_eq!(bitify_hex("a0c2017"), "10100000110000100000000101110000")
+        assert_eq!(bitify_hex("a0c2017"), "1010000011000010000000010111")
+    }
+
+    #[test]
+    fn rows_match_art() {
+        let art = hash_art("flqrgnkx");
+        let grid = disk_grid("flqrgnkx");
+        for (y, row) in art.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                assert_eq!(grid.get(x, y), c == '1');
+            }
+        }
     }
 
     #[test]
     fn test_part_one() {
         let test_inp = "flqrgnkx";
-        let r = part_one(&test_inp);
+        let r = part_one(test_inp);
         assert_eq!(r, 8108)
     }
 
     #[test]
     fn test_part_two() {
         let test_inp = "flqrgnkx";
-        let r = part_two(&test_inp);
+        let r = part_two(test_inp);
         assert_eq!(r, 1242);
     }
 }
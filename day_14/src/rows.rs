@@ -0,0 +1,76 @@
+//! Hashing disk rows straight to bits, spread across threads.
+
+use std::cmp;
+use std::thread;
+
+use knot_hash;
+
+/// The number of rows (and columns) in a disk.
+pub const DISK_SIZE: usize = 128;
+
+/// One row of a disk, with column 0 in the most significant bit.
+pub fn row_bits(key: &str, row: usize) -> u128 {
+    u128::from_be_bytes(knot_hash::hash(format!("{}-{}", key, row).as_bytes()))
+}
+
+/// All the rows of the disk for `key`, hashed in parallel.
+pub fn disk_rows(key: &str) -> Vec<u128> {
+    let rows = (0..DISK_SIZE).collect::<Vec<_>>();
+    par_map(&rows, |&row| row_bits(key, row))
+}
+
+/// The disks for many keys, in the same order as `keys`. Work is split by
+/// key rather than by row, which keeps every thread busy with less
+/// coordination.
+pub fn disk_rows_batch<S: AsRef<str> + Sync>(keys: &[S]) -> Vec<Vec<u128>> {
+    par_map(keys, |key| {
+        (0..DISK_SIZE).map(|row| row_bits(key.as_ref(), row)).collect()
+    })
+}
+
+/// The number of used squares.
+pub fn used(rows: &[u128]) -> usize {
+    rows.iter().map(|r| r.count_ones() as usize).sum()
+}
+
+/// Applies `f` to each item on a pool of scoped threads, one contiguous
+/// chunk per thread, returning results in the original order.
+fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+    where T: Sync, R: Send, F: Fn(&T) -> R + Sync
+{
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = cmp::max(1, items.len().div_ceil(threads));
+    let f = &f;
+    thread::scope(|scope| {
+        let handles = items.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles.into_iter()
+            .flat_map(|h| h.join().expect("hashing thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let rows = disk_rows("flqrgnkx");
+        assert_eq!(rows.len(), DISK_SIZE);
+        // ##.#.#..
+        assert_eq!(rows[0] >> 120, 0b1101_0100);
+        assert_eq!(used(&rows), 8108);
+    }
+
+    #[test]
+    fn batch_order() {
+        let keys = ["flqrgnkx", "hxtvlmkl", "flqrgnkx"];
+        let disks = disk_rows_batch(&keys);
+        assert_eq!(disks.len(), 3);
+        assert_eq!(disks[0], disks[2]);
+        assert_eq!(disks[1], disk_rows("hxtvlmkl"));
+        assert_eq!(par_map(&[1, 2, 3, 4, 5], |x| x * 2), vec![2, 4, 6, 8, 10]);
+    }
+}
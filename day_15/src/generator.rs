@@ -0,0 +1,162 @@
+//! The judge's generators: multiplicative LCGs that can jump ahead.
+
+/// The modulus both puzzle generators use, 2^31 - 1.
+pub const MODULUS: u64 = 2_147_483_647;
+pub const FACTOR_A: u64 = 16807;
+pub const FACTOR_B: u64 = 48271;
+
+/// Produces `value * factor % modulus` forever, starting from a seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generator {
+    value: u64,
+    factor: u64,
+    modulus: u64,
+}
+
+impl Generator {
+    /// A generator with the puzzle's modulus.
+    pub fn new(seed: u64, factor: u64) -> Self {
+        Generator::with_modulus(seed, factor, MODULUS)
+    }
+
+    pub fn with_modulus(seed: u64, factor: u64, modulus: u64) -> Self {
+        assert!(modulus > 0, "modulus must be positive");
+        Generator { value: seed % modulus, factor: factor % modulus, modulus }
+    }
+
+    /// The most recently produced value (or the seed, before any).
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn factor(&self) -> u64 {
+        self.factor
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+    /// Moves `n` values ahead in O(log n), as if `next` had been called `n`
+    /// times: the nth value is just `seed * factor^n`.
+    pub fn advance(&mut self, n: u64) {
+        self.value = mul_mod(self.value, pow_mod(self.factor, n, self.modulus), self.modulus);
+    }
+
+    /// Like `Iterator::skip`, but without stepping through what's skipped.
+    pub fn skip(mut self, n: u64) -> Self {
+        self.advance(n);
+        self
+    }
+
+    /// Splits the next `len` values into `parts` contiguous runs, returning
+    /// a generator positioned at the start of each, with its length.
+    pub fn split(&self, len: u64, parts: u64) -> Vec<(Generator, u64)> {
+        let parts = parts.clamp(1, len.max(1));
+        let base = len / parts;
+        let extra = len % parts;
+        let mut start = self.clone();
+        (0..parts)
+            .map(|i| {
+                let run = base + if i < extra { 1 } else { 0 };
+                let here = start.clone();
+                start.advance(run);
+                (here, run)
+            })
+            .collect()
+    }
+}
+
+impl Iterator for Generator {
+    type Item = u64;
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        self.value = if self.modulus == MODULUS {
+            mersenne_mul(self.value as u32, self.factor as u32) as u64
+        } else {
+            mul_mod(self.value, self.factor, self.modulus)
+        };
+        Some(self.value)
+    }
+}
+
+/// `a * b % MODULUS`, for `a` and `b` already reduced.
+///
+/// Since 2^31 ≡ 1 (mod 2^31 - 1), the high bits of the product can just be
+/// added to the low ones. Two folds are always enough: the result could
+/// only come out as `MODULUS` itself if the product were a multiple of it,
+/// and since the modulus is prime that means `a` or `b` was 0, so the
+/// product was too.
+#[inline]
+pub fn mersenne_mul(a: u32, b: u32) -> u32 {
+    let product = a as u64 * b as u64;
+    let folded = ((product & MODULUS) + (product >> 31)) as u32;
+    (folded & MODULUS as u32) + (folded >> 31)
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (a as u128 * b as u128 % modulus as u128) as u64
+}
+
+/// `base^exp % modulus`, by repeated squaring.
+pub fn pow_mod(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepping() {
+        let gen = Generator::new(65, FACTOR_A);
+        assert_eq!(gen.take(5).collect::<Vec<_>>(),
+                   vec![1092455, 1181022009, 245556042, 1744312007, 1352636452]);
+        assert_eq!(Generator::new(8921, FACTOR_B).skip(5).value(), 285222916);
+    }
+
+    #[test]
+    fn jumping_matches_stepping() {
+        let mut stepped = Generator::with_modulus(12345, 69069, 1 << 32);
+        for n in 0..1000u64 {
+            assert_eq!(Generator::with_modulus(12345, 69069, 1 << 32).skip(n).value(), stepped.value());
+            stepped.next();
+        }
+        assert_eq!(pow_mod(3, 0, 1), 0);
+        assert_eq!(pow_mod(2, 62, u64::MAX), 1 << 62);
+    }
+
+    #[test]
+    fn mersenne() {
+        let mut seed = 1u64;
+        for _ in 0..10_000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let (a, b) = ((seed >> 33) % MODULUS, (seed & 0x7fff_ffff) % MODULUS);
+            assert_eq!(mersenne_mul(a as u32, b as u32) as u64, mul_mod(a, b, MODULUS));
+        }
+        let max = MODULUS as u32 - 1;
+        assert_eq!(mersenne_mul(max, max), 1);
+        assert_eq!(mersenne_mul(max, 0), 0);
+    }
+
+    #[test]
+    fn splitting() {
+        let gen = Generator::new(65, FACTOR_A);
+        let parts = gen.split(10, 3);
+        assert_eq!(parts.iter().map(|p| p.1).collect::<Vec<_>>(), vec![4, 3, 3]);
+        let joined = parts.into_iter()
+            .flat_map(|(g, len)| g.take(len as usize))
+            .collect::<Vec<_>>();
+        assert_eq!(joined, gen.take(10).collect::<Vec<_>>());
+        assert_eq!(Generator::new(1, 2).split(0, 4).len(), 1);
+    }
+}
@@ -0,0 +1,287 @@
+//! A configurable judge: which generators, which values they offer, how
+//! many pairs are compared and how many bits have to agree.
+
+use std::thread;
+
+use generator::{Generator, FACTOR_A, FACTOR_B, MODULUS};
+use lanes;
+
+/// Every generator repeats within this many values, so a criterion that
+/// rejects this many in a row will never accept another.
+const GIVE_UP_AFTER: u64 = MODULUS - 1;
+
+/// Which values a generator hands to the judge.
+///
+/// Generated values lie in `1..MODULUS`, unless the seed or factor is a
+/// multiple of `MODULUS`, in which case they're all zero. A criterion
+/// that accepts none of a generator's values leaves the judge without
+/// pairs; see `Judge::count`.
+#[derive(Debug, Clone, Copy)]
+pub enum Criterion {
+    Any,
+    MultipleOf(u32),
+    Custom(fn(u32) -> bool),
+}
+
+impl Criterion {
+    pub fn accepts(&self, value: u32) -> bool {
+        match *self {
+            Criterion::Any => true,
+            Criterion::MultipleOf(m) => value.is_multiple_of(m),
+            Criterion::Custom(f) => f(value),
+        }
+    }
+
+    /// A mask that accepted values have no bits of, if the criterion can
+    /// be written that way. Checking a mask is much cheaper than a
+    /// division or a call through a pointer.
+    fn as_mask(&self) -> Option<u32> {
+        match *self {
+            Criterion::Any => Some(0),
+            Criterion::MultipleOf(m) if m.is_power_of_two() => Some(m - 1),
+            _ => None,
+        }
+    }
+
+    /// How many low bits are always zero in accepted values.
+    fn zero_low_bits(&self) -> u32 {
+        match *self {
+            Criterion::MultipleOf(m) if m > 0 => m.trailing_zeros(),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorConfig {
+    pub seed: u64,
+    pub factor: u64,
+    pub criterion: Criterion,
+}
+
+impl GeneratorConfig {
+    pub fn generator(&self) -> Generator {
+        Generator::new(self.seed, self.factor)
+    }
+
+    /// Whether the criterion plainly rejects every value the generator
+    /// makes. Custom criteria can't be checked ahead of time.
+    fn never_accepted(&self) -> bool {
+        let zeros = self.seed.is_multiple_of(MODULUS) || self.factor.is_multiple_of(MODULUS);
+        match self.criterion {
+            Criterion::MultipleOf(m) => !zeros && (m == 0 || m as u64 >= MODULUS),
+            _ => false,
+        }
+    }
+
+    /// The values offered to the judge, ending if the generator runs dry.
+    fn offered(&self) -> impl Iterator<Item = u32> {
+        let criterion = self.criterion;
+        self.generator()
+            .map(|v| v as u32)
+            .scan(0, move |rejected, v| {
+                if criterion.accepts(v) {
+                    *rejected = 0;
+                    Some(Some(v))
+                } else {
+                    *rejected += 1;
+                    if *rejected >= GIVE_UP_AFTER { None } else { Some(None) }
+                }
+            })
+            .flatten()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Judge {
+    pub a: GeneratorConfig,
+    pub b: GeneratorConfig,
+    /// How many pairs are compared.
+    pub pairs: u64,
+    /// How many low bits have to agree for a match, up to 32.
+    pub mask_bits: u32,
+}
+
+/// What a judge saw.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub matches: u64,
+    /// How many matches there'd be on average if the generators' values
+    /// were independent and uniform, given their criteria.
+    pub expected: f64,
+    /// The 1-based pair numbers of the first few matches.
+    pub first_matches: Vec<u64>,
+}
+
+impl Judge {
+    /// The judge from part one, for the given seeds.
+    pub fn part_one(seed_a: u64, seed_b: u64) -> Self {
+        Judge {
+            a: GeneratorConfig { seed: seed_a, factor: FACTOR_A, criterion: Criterion::Any },
+            b: GeneratorConfig { seed: seed_b, factor: FACTOR_B, criterion: Criterion::Any },
+            pairs: 40_000_000,
+            mask_bits: 16,
+        }
+    }
+
+    /// The pickier judge from part two, for the given seeds.
+    pub fn part_two(seed_a: u64, seed_b: u64) -> Self {
+        let mut judge = Judge::part_one(seed_a, seed_b);
+        judge.a.criterion = Criterion::MultipleOf(4);
+        judge.b.criterion = Criterion::MultipleOf(8);
+        judge.pairs = 5_000_000;
+        judge
+    }
+
+    /// Whether either generator plainly rejects every value, in which
+    /// case there's no point looking for pairs.
+    fn starved(&self) -> bool {
+        self.a.never_accepted() || self.b.never_accepted()
+    }
+
+    fn mask(&self) -> u32 {
+        if self.mask_bits >= 32 { u32::MAX } else { (1 << self.mask_bits) - 1 }
+    }
+
+    /// Counts the matching pairs.
+    ///
+    /// Unfiltered generators can jump ahead, so their pairs are split into
+    /// ranges judged on separate threads. Filtered ones have to be walked
+    /// in order.
+    ///
+    /// If a generator's criterion stops accepting values, it has run dry,
+    /// and only the pairs made before then are judged. That's none at all
+    /// if it can be seen up front, and otherwise found out after a whole
+    /// period of the generator goes by without a value being accepted.
+    pub fn count(&self) -> u64 {
+        if self.starved() {
+            return 0
+        }
+        let (gen_a, gen_b) = (self.a.generator(), self.b.generator());
+        let mask = self.mask();
+        match (self.a.criterion, self.b.criterion) {
+            (Criterion::Any, Criterion::Any) => {
+                let threads = thread::available_parallelism().map(|n| n.get() as u64).unwrap_or(1);
+                let ranges = gen_a.split(self.pairs, threads).into_iter()
+                    .zip(gen_b.split(self.pairs, threads));
+                thread::scope(|scope| {
+                    ranges
+                        .map(|((a, len), (b, _))| scope.spawn(move || lanes::judge(&a, &b, len, mask)))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .map(|h| h.join().unwrap())
+                        .sum()
+                })
+            }
+            (crit_a, crit_b) => match (crit_a.as_mask(), crit_b.as_mask()) {
+                (Some(ma), Some(mb)) => lanes::judge_filtered(
+                    &gen_a, |v| v & ma == 0, &gen_b, |v| v & mb == 0, self.pairs, mask, GIVE_UP_AFTER),
+                _ => lanes::judge_filtered(&gen_a, |v| crit_a.accepts(v), &gen_b, |v| crit_b.accepts(v),
+                                           self.pairs, mask, GIVE_UP_AFTER),
+            },
+        }
+    }
+
+    /// The chance that a single pair matches, if the generators were
+    /// independent. Bits that both criteria force to zero always agree.
+    pub fn match_probability(&self) -> f64 {
+        let fixed = self.a.criterion.zero_low_bits().min(self.b.criterion.zero_low_bits());
+        0.5f64.powi(self.mask_bits.saturating_sub(fixed).min(32) as i32)
+    }
+
+    /// The match count, what chance alone would give, and the pair numbers
+    /// of (up to) the first `k` matches.
+    pub fn stats(&self, k: usize) -> Stats {
+        if self.starved() {
+            return Stats { matches: 0, expected: 0.0, first_matches: Vec::new() }
+        }
+        let mask = self.mask();
+        let first_matches = self.a.offered()
+            .zip(self.b.offered())
+            .take(self.pairs as usize)
+            .enumerate()
+            .filter(|&(_, (a, b))| (a ^ b) & mask == 0)
+            .map(|(i, _)| i as u64 + 1)
+            .take(k)
+            .collect();
+        Stats {
+            matches: self.count(),
+            expected: self.pairs as f64 * self.match_probability(),
+            first_matches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let one = Judge::part_one(65, 8921);
+        assert_eq!(one.count(), 588);
+        let two = Judge::part_two(65, 8921);
+        assert_eq!(two.count(), 309);
+
+        let mut short = one;
+        short.pairs = 5;
+        let stats = short.stats(10);
+        assert_eq!(stats.matches, 1);
+        assert_eq!(stats.first_matches, vec![3]);
+
+        let stats = two.stats(1);
+        assert_eq!(stats.first_matches, vec![1056]);
+        // a's values are multiples of 4, so two bits always agree
+        assert_eq!(two.match_probability(), 0.5f64.powi(14));
+        assert!((stats.expected - 5_000_000.0 / 16384.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn custom() {
+        fn odd(v: u32) -> bool { v % 2 == 1 }
+        let mut judge = Judge::part_two(65, 8921);
+        judge.a.criterion = Criterion::Custom(odd);
+        judge.b.criterion = Criterion::Any;
+        judge.mask_bits = 4;
+        judge.pairs = 10_000;
+        let slow = judge.a.generator().filter(|&v| odd(v as u32))
+            .zip(judge.b.generator())
+            .take(10_000)
+            .filter(|&(a, b)| (a ^ b) & 0xf == 0)
+            .count();
+        assert_eq!(judge.count(), slow as u64);
+        assert_eq!(judge.match_probability(), 1.0 / 16.0);
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let none = Stats { matches: 0, expected: 0.0, first_matches: Vec::new() };
+        let mut judge = Judge::part_two(65, 8921);
+        judge.b.criterion = Criterion::MultipleOf(0);
+        assert!(!Criterion::MultipleOf(0).accepts(8));
+        assert_eq!(judge.count(), 0);
+        assert_eq!(judge.stats(5), none);
+
+        // every value is below 2^31 - 1
+        judge.pairs = 1;
+        judge.b.criterion = Criterion::MultipleOf(1 << 31);
+        assert_eq!(judge.count(), 0);
+        assert_eq!(judge.stats(5), none);
+    }
+
+    #[test]
+    fn all_zeros() {
+        // a seed of 0 makes every value 0, which is a multiple of anything
+        let mut judge = Judge::part_two(65, 0);
+        judge.b.criterion = Criterion::MultipleOf(0);
+        judge.pairs = 1000;
+        let slow = judge.a.generator().filter(|v| v % 4 == 0)
+            .take(1000)
+            .filter(|&a| a & 0xffff == 0)
+            .count();
+        assert_eq!(judge.count(), slow as u64);
+        judge.b.criterion = Criterion::MultipleOf(1 << 31);
+        assert_eq!(judge.count(), slow as u64);
+        assert_eq!(judge.stats(1).matches, slow as u64);
+    }
+}
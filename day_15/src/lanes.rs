@@ -0,0 +1,207 @@
+//! Running a generator as several independent lanes, so that values can
+//! be produced and compared a block at a time.
+//!
+//! Lane `i` starts at the generator's `i + 1`th value and steps by
+//! `factor^LANES`, so one step of every lane yields the next `LANES`
+//! values in order. The lanes don't depend on each other, which lets the
+//! compiler vectorise the multiplies and the comparisons.
+
+use generator::{mersenne_mul, pow_mod, Generator, MODULUS};
+
+/// The number of values produced per block.
+pub const LANES: usize = 8;
+
+/// How many filtered values are gathered before they're compared.
+const CHUNK: usize = 1024;
+
+/// How many blocks go by between checks that a filter is still accepting
+/// anything.
+const DRY_WINDOW: u64 = 64;
+
+#[derive(Debug, Clone)]
+pub struct LaneGenerator {
+    values: [u32; LANES],
+    factor: u32,
+}
+
+impl LaneGenerator {
+    /// Panics unless `gen` uses the puzzle's modulus.
+    pub fn new(gen: &Generator) -> Self {
+        assert_eq!(gen.modulus(), MODULUS, "lanes need the Mersenne modulus");
+        let mut values = [0; LANES];
+        let mut g = gen.clone();
+        for v in values.iter_mut() {
+            *v = g.next().unwrap() as u32;
+        }
+        LaneGenerator { values, factor: pow_mod(gen.factor(), LANES as u64, MODULUS) as u32 }
+    }
+
+    /// The next `LANES` values of the generator.
+    #[inline]
+    pub fn next_block(&mut self) -> [u32; LANES] {
+        let block = self.values;
+        for v in self.values.iter_mut() {
+            *v = mersenne_mul(*v, self.factor);
+        }
+        block
+    }
+}
+
+/// The values of a `LaneGenerator` that satisfy a predicate, gathered in
+/// chunks.
+#[derive(Debug, Clone)]
+pub struct Filtered<F> {
+    lanes: LaneGenerator,
+    predicate: F,
+    buf: Vec<u32>,
+    /// How many values at the front of `buf` have been accepted.
+    len: usize,
+    /// How many of those were handed out by the last `take`.
+    taken: usize,
+    /// How many values in a row may be rejected before giving up.
+    give_up_after: u64,
+}
+
+impl<F: Fn(u32) -> bool> Filtered<F> {
+    /// Gives up after at least `give_up_after` values in a row are rejected.
+    pub fn new(gen: &Generator, predicate: F, give_up_after: u64) -> Self {
+        Filtered {
+            lanes: LaneGenerator::new(gen),
+            predicate,
+            buf: vec![0; CHUNK + LANES],
+            len: 0,
+            taken: 0,
+            give_up_after,
+        }
+    }
+
+    /// The next `n` accepted values, for `n` up to `CHUNK`. Fewer are
+    /// returned if the predicate stops accepting anything.
+    ///
+    /// Every value in a block is written out, but the write position only
+    /// moves past the ones that are accepted, so there's no branch on the
+    /// predicate to mispredict.
+    #[inline]
+    fn take(&mut self, n: usize) -> &[u32] {
+        debug_assert!(n <= CHUNK);
+        // whatever overshot last time's request goes to the front
+        self.buf.copy_within(self.taken..self.len, 0);
+        self.len -= self.taken;
+        // checking for a dry spell every block would cost a branch on the
+        // predicate, so look once per window; only windows without a
+        // single accepted value count, which can only underestimate
+        let (mut blocks, mut mark, mut rejected) = (0u64, self.len, 0);
+        while self.len < n {
+            for v in &self.lanes.next_block() {
+                self.buf[self.len] = *v;
+                self.len += (self.predicate)(*v) as usize;
+            }
+            blocks += 1;
+            if blocks.is_multiple_of(DRY_WINDOW) {
+                if self.len > mark {
+                    rejected = 0;
+                    mark = self.len;
+                } else {
+                    rejected += DRY_WINDOW * LANES as u64;
+                    if rejected >= self.give_up_after {
+                        self.taken = self.len;
+                        return &self.buf[..self.len]
+                    }
+                }
+            }
+        }
+        self.taken = n;
+        &self.buf[..n]
+    }
+}
+
+/// How many of the pairs in two equal-length slices agree on the bits in
+/// `mask`.
+#[inline]
+fn count_matches(a: &[u32], b: &[u32], mask: u32) -> u64 {
+    a.iter().zip(b.iter())
+        .map(|(x, y)| ((x ^ y) & mask == 0) as u64)
+        .sum()
+}
+
+/// Counts pairs agreeing on the bits in `mask` among the next `pairs`
+/// values of each generator.
+pub fn judge(gen_a: &Generator, gen_b: &Generator, pairs: u64, mask: u32) -> u64 {
+    let (mut a, mut b) = (LaneGenerator::new(gen_a), LaneGenerator::new(gen_b));
+    let mut count = 0;
+    for _ in 0..pairs / LANES as u64 {
+        count += count_matches(&a.next_block(), &b.next_block(), mask);
+    }
+    let tail = (pairs % LANES as u64) as usize;
+    count + count_matches(&a.next_block()[..tail], &b.next_block()[..tail], mask)
+}
+
+/// Like `judge`, but each generator only offers values that pass its
+/// predicate.
+///
+/// A generator that rejects `give_up_after` values in a row is taken to
+/// have run dry; the count then covers the pairs judged so far.
+pub fn judge_filtered<FA, FB>(gen_a: &Generator, pred_a: FA, gen_b: &Generator, pred_b: FB,
+                              pairs: u64, mask: u32, give_up_after: u64) -> u64
+    where FA: Fn(u32) -> bool, FB: Fn(u32) -> bool
+{
+    let mut a = Filtered::new(gen_a, pred_a, give_up_after);
+    let mut b = Filtered::new(gen_b, pred_b, give_up_after);
+    let mut remaining = pairs;
+    let mut count = 0;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK as u64) as usize;
+        let (some_a, some_b) = (a.take(n), b.take(n));
+        let len = some_a.len().min(some_b.len());
+        count += count_matches(&some_a[..len], &some_b[..len], mask);
+        if len < n {
+            break
+        }
+        remaining -= n as u64;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generator::{FACTOR_A, FACTOR_B};
+
+    #[test]
+    fn lanes_match_generator() {
+        let gen = Generator::new(65, FACTOR_A);
+        let mut lanes = LaneGenerator::new(&gen);
+        let blocks = (0..5).flat_map(|_| lanes.next_block().to_vec()).collect::<Vec<_>>();
+        let expected = gen.clone().take(5 * LANES).map(|v| v as u32).collect::<Vec<_>>();
+        assert_eq!(blocks, expected);
+
+        let mut filtered = Filtered::new(&gen, |v| v % 4 == 0, u64::MAX);
+        let mut taken = filtered.take(3).to_vec();
+        taken.extend_from_slice(filtered.take(100));
+        taken.extend_from_slice(filtered.take(CHUNK));
+        let expected = gen.filter(|v| v % 4 == 0).take(103 + CHUNK).map(|v| v as u32).collect::<Vec<_>>();
+        assert_eq!(taken, expected);
+    }
+
+    #[test]
+    fn example() {
+        let (a, b) = (Generator::new(65, FACTOR_A), Generator::new(8921, FACTOR_B));
+        assert_eq!(judge(&a, &b, 5, 0xffff), 1);
+        assert_eq!(judge(&a, &b, 40_000_000, 0xffff), 588);
+        let picky = |pairs| judge_filtered(&a, |v| v % 4 == 0, &b, |v| v % 8 == 0, pairs, 0xffff, 1000);
+        assert_eq!(picky(1055), 0);
+        assert_eq!(picky(1056), 1);
+        assert_eq!(picky(5_000_000), 309);
+    }
+
+    #[test]
+    fn running_dry() {
+        let gen = Generator::new(65, FACTOR_A);
+        let mut never = Filtered::new(&gen, |_| false, 1000);
+        assert!(never.take(1).is_empty());
+
+        // a runs dry after its first value, so only that pair is judged
+        let first = gen.clone().next().unwrap() as u32;
+        assert_eq!(judge_filtered(&gen, |v| v == first, &gen, |_| true, 100, u32::MAX, 1000), 1);
+    }
+}
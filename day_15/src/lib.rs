@@ -0,0 +1,5 @@
+//! The generators from day fifteen, and tools for judging them.
+
+pub mod generator;
+pub mod lanes;
+pub mod judge;
@@ -1,89 +1,58 @@
-fn main() {
-    let p1 = part_one();
-    println!("part one: {}", p1);
-
-    let p2 = part_two();
-    println!("part two: {}", p2);
-}
-
-static A_FACTOR: usize = 16807;
-static B_FACTOR: usize = 48271;
+extern crate day_15;
 
-fn part_one() -> usize {
-    let mut cur_a = 883;
-    let mut cur_b = 879;
-    let mut count = 0;
-
-    for _i in 0..40_000_000 {
-        let next_a = next_value(cur_a, A_FACTOR);
-        let next_b = next_value(cur_b, B_FACTOR);
-        if low_16_match(next_a, next_b) { count += 1 }
-        cur_a = next_a;
-        cur_b = next_b;
-    }
-    count
-}
+use std::env;
+use std::thread;
 
+use day_15::judge::Judge;
 
-fn part_two() -> usize {
-    let cur_a = 883;
-    let cur_b = 879;
-    let gen_a = ValueGenerator::new(cur_a, A_FACTOR);
-    let gen_b = ValueGenerator::new(cur_b, B_FACTOR);
-    gen_a.filter(|v| v % 4 == 0).zip(gen_b.filter(|v| v % 8 == 0))
-        .filter(|&(v1, v2)| low_16_match(v1, v2))
-        .count()
-}
-
-fn next_value(prev_val: usize, factor: usize) -> usize {
-    let divisor = 2147483647;
-    (prev_val * factor) % divisor
-}
+static A_SEED: u64 = 883;
+static B_SEED: u64 = 879;
 
-fn low_16_match(n1: usize, n2: usize) -> bool {
-    n1 % 2usize.pow(16) == n2 % 2usize.pow(16)
-}
-
-struct ValueGenerator {
-    cur_val: usize,
-    nb_runs: usize,
-    constant_factor: usize,
-}
-
-impl ValueGenerator {
-    fn new(cur_val: usize, constant_factor: usize) -> Self {
-        let nb_runs = 0;
-        ValueGenerator { cur_val, nb_runs, constant_factor }
+fn main() {
+    let judges = [Judge::part_one(A_SEED, B_SEED), Judge::part_two(A_SEED, B_SEED)];
+
+    if env::args().nth(1).as_deref() == Some("--stats") {
+        let k = env::args().nth(2).map(|k| k.parse().expect("k should be a number")).unwrap_or(5);
+        for (i, judge) in judges.iter().enumerate() {
+            let stats = judge.stats(k);
+            println!("part {}: {} matches, {:.1} expected by chance; first at {:?}",
+                     i + 1, stats.matches, stats.expected, stats.first_matches);
+        }
+        return
     }
-}
 
-impl Iterator for ValueGenerator {
-    type Item = usize;
-    fn next(&mut self) -> Option<usize> {
-        if self.nb_runs == 40_000_000 { return None }
-        let next_val = next_value(self.cur_val, self.constant_factor);
-        self.nb_runs += 1;
-        self.cur_val = next_val;
-        Some(next_val)
-    }
+    let (p1, p2) = thread::scope(|scope| {
+        let p2 = scope.spawn(|| judges[1].count());
+        (judges[0].count(), p2.join().unwrap())
+    });
+    println!("part one: {}", p1);
+    println!("part two: {}", p2);
 }
 
-
-
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use day_15::generator::{Generator, FACTOR_A, FACTOR_B};
+    use day_15::judge::Judge;
+    use day_15::lanes;
 
     #[test]
     fn gen_vals() {
-        assert_eq!(next_value(65, 16807), 1092455);
-        assert_eq!(next_value(1092455, 16807), 1181022009);
+        let mut gen = Generator::new(65, 16807);
+        assert_eq!(gen.next(), Some(1092455));
+        assert_eq!(gen.next(), Some(1181022009));
     }
 
     #[test]
-    fn bin_match() {
-        let v1 = 245556042;
-        let v2 = 1431495498;
-        assert!(low_16_match(v1, v2));
+    fn example() {
+        // only the third pair, 245556042 and 1431495498, match
+        let (gen_a, gen_b) = (Generator::new(65, FACTOR_A), Generator::new(8921, FACTOR_B));
+        assert_eq!(lanes::judge(&gen_a, &gen_b, 2, 0xffff), 0);
+        assert_eq!(lanes::judge(&gen_a, &gen_b, 5, 0xffff), 1);
+
+        let mut picky = Judge::part_two(65, 8921);
+        picky.pairs = 1056;
+        assert_eq!(picky.count(), 1);
+        picky.pairs = 1055;
+        assert_eq!(picky.count(), 0);
     }
 }
@@ -0,0 +1,79 @@
+//! Finding where a repeated process starts going round in circles.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The states a process passes through before it repeats.
+///
+/// The states from `start` onwards repeat every `length` steps; the ones
+/// before `start` (the "tail") are never seen again.
+#[derive(Debug, Clone)]
+pub struct Cycle<S> {
+    pub start: usize,
+    pub length: usize,
+    states: Vec<S>,
+}
+
+impl<S: Clone + Eq + Hash> Cycle<S> {
+    /// Applies `step` from `initial` until some state comes up a second
+    /// time. The initial state needn't be part of the cycle.
+    pub fn find<F>(initial: S, mut step: F) -> Self
+        where F: FnMut(&S) -> S
+    {
+        let mut seen = HashMap::new();
+        let mut states = Vec::new();
+        let mut state = initial;
+        loop {
+            if let Some(&start) = seen.get(&state) {
+                let length = states.len() - start;
+                return Cycle { start, length, states }
+            }
+            let next = step(&state);
+            seen.insert(state.clone(), states.len());
+            states.push(state);
+            state = next;
+        }
+    }
+
+    /// The state after `n` steps.
+    pub fn state_after(&self, n: u64) -> &S {
+        let n = if n < self.start as u64 {
+            n as usize
+        } else {
+            self.start + ((n - self.start as u64) % self.length as u64) as usize
+        };
+        &self.states[n]
+    }
+
+    /// Every distinct state, in the order they were reached.
+    pub fn states(&self) -> &[S] {
+        &self.states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_a_tail() {
+        // 0, 1, 2, 5, 26, 677, 330, ... which takes a while to settle
+        let step = |&x: &u64| (x * x + 1) % 1000;
+        let cycle = Cycle::find(0u64, step);
+        assert!(cycle.start > 0);
+        let mut x = 0;
+        for n in 0..5000 {
+            assert_eq!(*cycle.state_after(n), x, "after {} steps", n);
+            x = step(&x);
+        }
+        assert_eq!(cycle.states().len(), cycle.start + cycle.length);
+    }
+
+    #[test]
+    fn fixed_point() {
+        let cycle = Cycle::find(3u8, |&x| if x > 0 { x - 1 } else { 0 });
+        assert_eq!((cycle.start, cycle.length), (3, 1));
+        assert_eq!(*cycle.state_after(1_000_000_000), 0);
+        assert_eq!(*cycle.state_after(1), 2);
+    }
+}
@@ -0,0 +1,231 @@
+//! Dance moves, and whole dances reduced to permutations.
+//!
+//! Spins and exchanges move dancers by position, whatever their names;
+//! partner swaps rename dancers, wherever they stand. The two kinds of
+//! move commute, so any dance is a reordering of positions followed by a
+//! relabelling, and repeating it is just repeating each of those.
+
+use std::str::FromStr;
+
+use cycle::Cycle;
+use fast::{compile, Line, Move};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Spin(usize),
+    Exchange(usize, usize),
+    Partner(char, char),
+}
+
+impl Op {
+    pub fn operate(&self, state: &mut [char]) {
+        match *self {
+            Op::Spin(n) => state.rotate_right(n),
+            Op::Exchange(one, two) => state.swap(one, two),
+            Op::Partner(one, two) => {
+                let idx_one = state.iter().position(|c| c == &one).unwrap();
+                let idx_two = state.iter().position(|c| c == &two).unwrap();
+                state[idx_one] = two;
+                state[idx_two] = one;
+            }
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty move".into())
+        }
+        let (op, rest) = s.split_at(1);
+        match op {
+            "s" => Ok(Op::Spin(rest.parse().map_err(|e| format!("{}", e))?)),
+            "x" => {
+                let mut nums = rest.split('/')
+                    .map(|n| n.parse::<usize>().map_err(|e| format!("bad exchange '{}': {}", s, e)));
+                match (nums.next(), nums.next(), nums.next()) {
+                    (Some(one), Some(two), None) => Ok(Op::Exchange(one?, two?)),
+                    _ => Err(format!("bad exchange '{}'", s)),
+                }
+            }
+            "p" => {
+                let rest = rest.chars().collect::<Vec<_>>();
+                match rest.as_slice() {
+                    &[p1, '/', p2] => Ok(Op::Partner(p1, p2)),
+                    _ => Err(format!("bad partner swap '{}'", s)),
+                }
+            }
+            _ => Err(format!("unexpected input {}", s)),
+        }
+    }
+}
+
+/// The line of `dancers` programs in their starting order. They're named
+/// `a` onwards; past `z` the names carry on through the following code
+/// points.
+pub fn line(dancers: usize) -> Vec<char> {
+    (0..dancers).map(name).collect()
+}
+
+pub(crate) fn name(index: usize) -> char {
+    char::from_u32('a' as u32 + index as u32).expect("too many dancers")
+}
+
+pub(crate) fn index(name: char) -> usize {
+    (name as u32).wrapping_sub('a' as u32) as usize
+}
+
+/// Dances `ops` over and over from `start`, one round per step, until the
+/// line repeats.
+pub fn find_cycle(ops: &[Op], start: &[char]) -> Cycle<Vec<char>> {
+    Cycle::find(start.to_owned(), |state| {
+        let mut next = state.clone();
+        for op in ops {
+            op.operate(&mut next);
+        }
+        next
+    })
+}
+
+/// A dance for a fixed number of dancers, as a pair of permutations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dance {
+    /// Position `i` ends up holding whoever started at `positions[i]`.
+    positions: Vec<usize>,
+    /// The dancer named `c` is renamed `labels[c]`.
+    labels: Vec<usize>,
+}
+
+impl Dance {
+    /// The dance that does nothing.
+    pub fn identity(dancers: usize) -> Self {
+        Dance { positions: (0..dancers).collect(), labels: (0..dancers).collect() }
+    }
+
+    /// Reduces `ops` to a single dance, checking that every move makes
+    /// sense for this many dancers.
+    pub fn new(ops: &[Op], dancers: usize) -> Result<Self, String> {
+        compile(ops, dancers).map(|moves| Dance::from_moves(&moves, dancers))
+    }
+
+    /// Reduces already compiled moves to a single dance.
+    pub fn from_moves(moves: &[Move], dancers: usize) -> Self {
+        let (by_position, by_name): (Vec<_>, Vec<_>) = moves.iter().cloned()
+            .partition(|m| !matches!(*m, Move::Partner(..)));
+        let mut positions = Line::new(dancers);
+        positions.perform(&by_position);
+        // who each name currently belongs to, by starting name
+        let mut named = (0..dancers).collect::<Vec<_>>();
+        for m in by_name {
+            if let Move::Partner(a, b) = m {
+                named.swap(a, b);
+            }
+        }
+        let mut labels = vec![0; dancers];
+        for (name, &original) in named.iter().enumerate() {
+            labels[original] = name;
+        }
+        Dance { positions: positions.order(), labels }
+    }
+
+    pub fn dancers(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// This dance followed by `other`.
+    pub fn then(&self, other: &Dance) -> Dance {
+        Dance {
+            positions: other.positions.iter().map(|&i| self.positions[i]).collect(),
+            labels: self.labels.iter().map(|&c| other.labels[c]).collect(),
+        }
+    }
+
+    /// This dance repeated `rounds` times, by repeated squaring.
+    pub fn pow(&self, mut rounds: u64) -> Dance {
+        let mut result = Dance::identity(self.dancers());
+        let mut square = self.clone();
+        while rounds > 0 {
+            if rounds & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            rounds >>= 1;
+        }
+        result
+    }
+
+    /// Performs the dance on a line of dancers named `a` onwards.
+    pub fn apply(&self, state: &[char]) -> Vec<char> {
+        assert_eq!(state.len(), self.dancers());
+        self.positions.iter()
+            .map(|&i| name(self.labels[index(state[i])]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(moves: &str) -> Vec<Op> {
+        moves.split(',').map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn example() {
+        let dance = Dance::new(&parse("s1,x3/4,pe/b"), 5).unwrap();
+        let start = line(5);
+        assert_eq!(dance.apply(&start).iter().collect::<String>(), "baedc");
+        assert_eq!(dance.pow(2).apply(&start).iter().collect::<String>(), "ceadb");
+        assert_eq!(dance.pow(0), Dance::identity(5));
+    }
+
+    #[test]
+    fn pow_matches_simulation() {
+        let ops = parse("x0/5,pa/c,s3,x2/7,pb/h,pd/a,s6,x1/4,pg/e,x3/6");
+        let dance = Dance::new(&ops, 8).unwrap();
+        let mut state = line(8);
+        for n in 0..200 {
+            assert_eq!(dance.pow(n).apply(&line(8)), state, "after {} rounds", n);
+            for op in &ops {
+                op.operate(&mut state);
+            }
+        }
+    }
+
+    #[test]
+    fn cycles() {
+        let ops = parse("s1,x3/4,pe/b");
+        let cycle = find_cycle(&ops, &line(5));
+        assert_eq!((cycle.start, cycle.length), (0, 4));
+        let dance = Dance::new(&ops, 5).unwrap();
+        assert_eq!(cycle.state_after(1_000_000_001), &dance.pow(1_000_000_001).apply(&line(5)));
+
+        // duplicate names make partner swaps lossy, so the start is lost:
+        // aadd, daad, adad, daad, ...
+        let start = "aadd".chars().collect::<Vec<_>>();
+        let cycle = find_cycle(&parse("pa/d"), &start);
+        assert_eq!((cycle.start, cycle.length), (1, 2));
+    }
+
+    #[test]
+    fn many_dancers() {
+        let ops = parse("s20,x0/39,p{/a,x5/30");
+        let mut state = line(40);
+        assert_eq!(state[26], '{');
+        for op in &ops {
+            op.operate(&mut state);
+        }
+        assert_eq!(Dance::new(&ops, 40).unwrap().apply(&line(40)), state);
+    }
+
+    #[test]
+    fn bad_moves() {
+        assert!(Dance::new(&parse("x1/5"), 5).is_err());
+        assert!(Dance::new(&parse("s6"), 5).is_err());
+        assert!(Dance::new(&parse("pa/f"), 5).is_err());
+        assert!("x1".parse::<Op>().is_err());
+        assert!("pab".parse::<Op>().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Compiling dance moves ahead of time, and a line of dancers that can
+//! perform them in constant time each.
+
+use dance::{name, index, Op};
+
+/// A move checked against the number of dancers, with partners' names
+/// already turned into indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    Spin(usize),
+    Exchange(usize, usize),
+    Partner(usize, usize),
+}
+
+/// Checks and resolves `ops` for this many dancers, and tidies up along
+/// the way: runs of spins become a single spin, and moves that do nothing
+/// are dropped.
+pub fn compile(ops: &[Op], dancers: usize) -> Result<Vec<Move>, String> {
+    let label = |c: char| {
+        let idx = index(c);
+        if idx < dancers { Ok(idx) } else { Err(format!("no dancer named '{}'", c)) }
+    };
+    let mut moves = Vec::with_capacity(ops.len());
+    for op in ops {
+        let next = match *op {
+            Op::Spin(n) if n <= dancers => Move::Spin(n),
+            Op::Exchange(a, b) if a < dancers && b < dancers => Move::Exchange(a, b),
+            Op::Partner(a, b) => Move::Partner(label(a)?, label(b)?),
+            ref op => return Err(format!("{:?} doesn't fit {} dancers", op, dancers)),
+        };
+        match (moves.last_mut(), next) {
+            (Some(&mut Move::Spin(ref mut prev)), Move::Spin(n)) => *prev = (*prev + n) % dancers,
+            (_, Move::Spin(n)) if n % dancers.max(1) == 0 => (),
+            (_, Move::Exchange(a, b)) | (_, Move::Partner(a, b)) if a == b => (),
+            (_, next) => moves.push(next),
+        }
+        if let Some(&Move::Spin(0)) = moves.last() {
+            moves.pop();
+        }
+    }
+    Ok(moves)
+}
+
+/// A line of dancers that never physically moves.
+///
+/// Spins only shift where the front of the line is, and an index of
+/// where each dancer stands makes partner swaps as cheap as exchanges.
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// Dancer indices, with the front of the line at `front`.
+    slots: Vec<usize>,
+    /// The slot each dancer is in.
+    slot_of: Vec<usize>,
+    front: usize,
+}
+
+impl Line {
+    /// `dancers` dancers in their starting order.
+    pub fn new(dancers: usize) -> Self {
+        Line { slots: (0..dancers).collect(), slot_of: (0..dancers).collect(), front: 0 }
+    }
+
+    /// A line in the given order, which must name each of the first
+    /// `state.len()` dancers exactly once.
+    pub fn from_names(state: &[char]) -> Result<Self, String> {
+        let mut slot_of = vec![usize::MAX; state.len()];
+        for (slot, &c) in state.iter().enumerate() {
+            match slot_of.get_mut(index(c)) {
+                Some(s) if *s == usize::MAX => *s = slot,
+                _ => return Err(format!("'{}' isn't a dancer, or appears twice", c)),
+            }
+        }
+        let slots = state.iter().map(|&c| index(c)).collect();
+        Ok(Line { slots, slot_of, front: 0 })
+    }
+
+    fn slot(&self, position: usize) -> usize {
+        let slot = self.front + position;
+        if slot >= self.slots.len() { slot - self.slots.len() } else { slot }
+    }
+
+    fn swap_slots(&mut self, a: usize, b: usize) {
+        self.slots.swap(a, b);
+        self.slot_of[self.slots[a]] = a;
+        self.slot_of[self.slots[b]] = b;
+    }
+
+    pub fn perform(&mut self, moves: &[Move]) {
+        let len = self.slots.len();
+        for m in moves {
+            match *m {
+                Move::Spin(n) => self.front = (self.front + len - n) % len,
+                Move::Exchange(a, b) => {
+                    let (a, b) = (self.slot(a), self.slot(b));
+                    self.swap_slots(a, b);
+                }
+                Move::Partner(a, b) => {
+                    let (a, b) = (self.slot_of[a], self.slot_of[b]);
+                    self.swap_slots(a, b);
+                }
+            }
+        }
+    }
+
+    /// The dancers' indices, front first.
+    pub fn order(&self) -> Vec<usize> {
+        (0..self.slots.len()).map(|p| self.slots[self.slot(p)]).collect()
+    }
+
+    pub fn names(&self) -> Vec<char> {
+        self.order().into_iter().map(name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dance::line;
+
+    fn parse(moves: &str) -> Vec<Op> {
+        moves.split(',').map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn compiling() {
+        let moves = compile(&parse("s1,s2,x3/3,pa/a,s2,x0/1,s5,pb/c"), 5).unwrap();
+        assert_eq!(moves, vec![Move::Exchange(0, 1), Move::Partner(1, 2)]);
+        let moves = compile(&parse("s1,s2,x0/1"), 5).unwrap();
+        assert_eq!(moves, vec![Move::Spin(3), Move::Exchange(0, 1)]);
+        assert!(compile(&parse("pa/z"), 5).is_err());
+    }
+
+    #[test]
+    fn matches_simulation() {
+        let ops = parse("s1,x3/4,pe/b,s3,s4,x0/4,pa/c,pc/d,s2,x1/2");
+        let moves = compile(&ops, 5).unwrap();
+        let mut fast = Line::new(5);
+        let mut slow = line(5);
+        for _ in 0..50 {
+            fast.perform(&moves);
+            for op in &ops {
+                op.operate(&mut slow);
+            }
+            assert_eq!(fast.names(), slow);
+        }
+        let from_names = Line::from_names(&slow).unwrap();
+        assert_eq!(from_names.names(), slow);
+        assert!(Line::from_names(&['a', 'a']).is_err());
+        assert!(Line::from_names(&['a', 'c']).is_err());
+    }
+}
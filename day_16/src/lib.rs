@@ -0,0 +1,5 @@
+//! The programs' dance from day sixteen.
+
+pub mod dance;
+pub mod cycle;
+pub mod fast;
@@ -1,9 +1,9 @@
-#![feature(slice_rotate)]
-use std::str::FromStr;
-use std::fmt;
-use std::mem;
-use std::time::{Instant, Duration};
+extern crate day_16;
 
+use std::env;
+
+use day_16::dance::{self, Dance, Op};
+use day_16::fast::{self, Line};
 
 fn main() {
     let input = include_str!("../input.txt").trim()
@@ -11,128 +11,32 @@ fn main() {
         .map(|s| s.parse::<Op>().unwrap())
         .collect::<Vec<_>>();
 
-    let state = "abcdefghijklmnop".chars().collect::<Vec<_>>();
+    let state = dance::line(16);
     let p1 = part_one(&input, &state, 1);
     println!("part one {}", p1.iter().collect::<String>());
 
-    let ONE_BILLLLLLION = 1_000_000_000;
-    let nb_ops = ONE_BILLLLLLION % 60;
-    let mut state = ['a', 'b', 'c', 'd', 'e','f','g','h','i','j','k','l','m','n','o','p'];
-
-    let p2 = part_one(&input, &state, nb_ops);
+    if env::args().nth(1).as_deref() == Some("--cycle") {
+        let cycle = dance::find_cycle(&input, &state);
+        println!("repeats every {} rounds after {}", cycle.length, cycle.start);
+        println!("part two {}", cycle.state_after(1_000_000_000).iter().collect::<String>());
+        return
+    }
 
+    let p2 = part_two(&input, &state, 1_000_000_000);
     println!("part two {}", p2.iter().collect::<String>());
-
 }
 
 fn part_one(ops: &[Op], state: &[char], runs: usize) -> Vec<char> {
-    let mut state = state.to_owned();
-    let initial_state = state.clone();
-    for _i in 0..runs {
-        for op in ops {
-            op.operate(&mut state);
-            //op.debug();
-            if _i % 1000 == 0 {
-                //println!("{}", _i);
-            }
-            //println!("{:?} {:?}", state, op);
-        }
-        if state == initial_state {
-            println!("repeated at {}", _i);
-        }
+    let moves = fast::compile(ops, state.len()).unwrap();
+    let mut line = Line::from_names(state).unwrap();
+    for _ in 0..runs {
+        line.perform(&moves);
     }
-    state
+    line.names()
 }
 
-
-// wrong path
-fn make_map(end_state: &[char]) -> Vec<usize> {
-    // for each char, and each index, find the starting index
-    // create a new vec, where for the value at each index is the final
-    // index of the char that started there.
-    let mut original_order = end_state.to_owned();
-    let mut result = Vec::new();
-    original_order.sort();
-    for c in end_state.iter() {
-        let end_idx = original_order.iter().position(|el| el == c).unwrap();
-        result.push(end_idx);
-    }
-    result
-}
-
-// unused, ultimately
-fn part_two(ops: &[Op], state: &[char], runs: usize) -> Vec<char> {
-    let mut sample_end_state = state.to_owned();
-    let mut state = state.to_owned();
-
-    for op in ops {
-        op.operate(&mut sample_end_state);
-    }
-
-    assert!(sample_end_state != state);
-    let op_map = make_map(&sample_end_state);
-
-    let mut new_state = state.clone();
-    for _i in 0..runs {
-        //if _i % 100_000 == 0 { println!("i: {}", _i) }
-        for j in 0..state.len() {
-            //new_state[j] = state[*op_map.get(&j).unwrap()];
-            new_state[j] = state[op_map[j]];
-        }
-        mem::swap(&mut state, &mut new_state);
-        //state = new_state;
-    }
-    state
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Op {
-    Spin(usize),
-    Exchange(usize, usize),
-    Partner(char, char),
-}
-
-impl Op {
-    fn operate(&self, state: &mut [char]) {
-        let s_len = state.len();
-        match *self {
-            Op::Spin(n) => state.rotate(s_len - n),
-            Op::Exchange(one, two) => {
-                let temp = state[one];
-                state[one] = state[two];
-                state[two] = temp;
-            }
-            Op::Partner(one, two) => {
-                let idx_one = state.iter().position(|c| c == &one).unwrap();
-                let idx_two = state.iter().position(|c| c == &two).unwrap();
-                state[idx_one] = two;
-                state[idx_two] = one;
-            }
-        }
-    }
-}
-
-impl FromStr for Op {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-		let (op, rest) = s.split_at(1);
-		match op {
-			"s" => Ok(Op::Spin(rest.parse().map_err(|e| format!("{}", e))?)),
-			"x" => {
-                let mut nums = rest.split('/')
-                    .map(|n| n.parse::<usize>().unwrap());
-                Ok(Op::Exchange(nums.next().unwrap(), nums.next().unwrap()))
-            }
-            "p" => {
-                let mut rest = rest.chars();
-                let p1 = rest.next().unwrap();
-                let _slash = rest.next();
-                let p2 = rest.next().unwrap();
-                Ok(Op::Partner(p1, p2))
-            }
-            _ => panic!("unexpected input {}", s),
-        }
-	}
+fn part_two(ops: &[Op], state: &[char], runs: u64) -> Vec<char> {
+    Dance::new(ops, state.len()).unwrap().pow(runs).apply(state)
 }
 
 #[cfg(test)]
@@ -158,4 +62,12 @@ mod tests {
         Op::Partner('e', 'b').operate(&mut inp);
         assert_eq!(inp, vec!['b', 'a', 'e', 'd', 'c']);
     }
+
+    #[test]
+    fn p2_test() {
+        let ops = vec![Op::Spin(1), Op::Exchange(3, 4), Op::Partner('e', 'b')];
+        let start = dance::line(5);
+        assert_eq!(part_two(&ops, &start, 2), part_one(&ops, &start, 2));
+        assert_eq!(part_two(&ops, &start, 1000), part_one(&ops, &start, 1000));
+    }
 }
@@ -0,0 +1,333 @@
+//! Compiles register programs to a flat instruction list over interned
+//! registers, for running large inputs without hashing register names.
+
+use std::collections::HashMap;
+
+use super::Op;
+use interp::{Action, Condition, Program, Value};
+
+/// Maps register names to dense indices, in order of first appearance.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interner {
+    indices: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(&idx) = self.indices.get(name) {
+            return idx
+        }
+        let idx = self.names.len();
+        self.indices.insert(name.to_owned(), idx);
+        self.names.push(name.to_owned());
+        idx
+    }
+
+    pub fn name(&self, idx: usize) -> &str {
+        &self.names[idx]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Register(usize),
+    Literal(isize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Add(usize, Operand),
+    Sub(usize, Operand),
+    Mul(usize, Operand),
+    Set(usize, Operand),
+    Jump(usize),
+    /// Jump to the target if the comparison holds.
+    Branch(Operand, Op, Operand, usize),
+}
+
+/// What to do when arithmetic overflows an `isize`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Stop with an error naming the offending source line.
+    Checked,
+    Wrapping,
+}
+
+#[derive(Debug, Clone)]
+pub struct Compiled {
+    pub instrs: Vec<Instr>,
+    /// The source line each instruction was compiled from.
+    pub lines: Vec<usize>,
+    pub registers: Interner,
+}
+
+/// The state of a running `Compiled` program.
+#[derive(Debug, Clone)]
+pub struct Executor {
+    pub registers: Vec<isize>,
+    pub pc: usize,
+    pub highest_seen: isize,
+    pub steps: usize,
+}
+
+impl Operand {
+    #[inline]
+    fn get(&self, registers: &[isize]) -> isize {
+        match *self {
+            Operand::Register(idx) => registers[idx],
+            Operand::Literal(val) => val,
+        }
+    }
+}
+
+impl Compiled {
+    /// Lowers a parsed program. Conditions become short-circuiting
+    /// branches, so `and`/`or` cost no more than the comparisons they run.
+    pub fn from_program(program: &Program) -> Self {
+        // the parser already interned every register, so keep its numbering
+        let mut builder = Builder { registers: program.registers.clone(), ..Builder::default() };
+        for (idx, statement) in program.statements.iter().enumerate() {
+            builder.statement_starts.push(builder.instrs.len());
+            let skip = builder.new_label();
+            builder.branch_unless(&statement.condition, skip);
+            let instr = match statement.action {
+                Action::Jump(target) => {
+                    builder.statement_jumps.push((builder.instrs.len(), target));
+                    Instr::Jump(target)
+                }
+                Action::Inc(reg, ref val) => Instr::Add(reg, operand(val)),
+                Action::Dec(reg, ref val) => Instr::Sub(reg, operand(val)),
+                Action::Mul(reg, ref val) => Instr::Mul(reg, operand(val)),
+                Action::Set(reg, ref val) => Instr::Set(reg, operand(val)),
+            };
+            builder.instrs.push(instr);
+            builder.place_label(skip);
+            let len = builder.instrs.len();
+            builder.lines.resize(len, statement.line);
+            debug_assert_eq!(builder.statement_starts.len(), idx + 1);
+        }
+        builder.finish()
+    }
+
+    pub fn executor(&self) -> Executor {
+        Executor { registers: vec![0; self.registers.len()], pc: 0, highest_seen: 0, steps: 0 }
+    }
+}
+
+impl Executor {
+    /// Runs until the program halts or `max_steps` instructions have
+    /// executed, whichever is first.
+    pub fn run(&mut self, program: &Compiled, overflow: Overflow, max_steps: Option<usize>)
+        -> Result<(), String>
+    {
+        let instrs = &program.instrs;
+        let max_steps = max_steps.unwrap_or(usize::MAX);
+        while self.pc < instrs.len() {
+            if self.steps >= max_steps {
+                return Err(format!("no halt after {} steps (pc = {})", self.steps, self.pc))
+            }
+            self.steps += 1;
+            let (reg, lhs, rhs) = match instrs[self.pc] {
+                Instr::Jump(target) => {
+                    self.pc = target;
+                    continue
+                }
+                Instr::Branch(ref lhs, ref op, ref rhs, target) => {
+                    if op.compare(lhs.get(&self.registers), rhs.get(&self.registers)) {
+                        self.pc = target;
+                    } else {
+                        self.pc += 1;
+                    }
+                    continue
+                }
+                Instr::Set(reg, ref val) => {
+                    let val = val.get(&self.registers);
+                    self.write(reg, val);
+                    continue
+                }
+                Instr::Add(reg, ref val) | Instr::Sub(reg, ref val) | Instr::Mul(reg, ref val) =>
+                    (reg, self.registers[reg], val.get(&self.registers)),
+            };
+            let result = match (&instrs[self.pc], overflow) {
+                (&Instr::Add(..), Overflow::Wrapping) => Some(lhs.wrapping_add(rhs)),
+                (&Instr::Sub(..), Overflow::Wrapping) => Some(lhs.wrapping_sub(rhs)),
+                (&Instr::Mul(..), Overflow::Wrapping) => Some(lhs.wrapping_mul(rhs)),
+                (&Instr::Add(..), Overflow::Checked) => lhs.checked_add(rhs),
+                (&Instr::Sub(..), Overflow::Checked) => lhs.checked_sub(rhs),
+                (&Instr::Mul(..), Overflow::Checked) => lhs.checked_mul(rhs),
+                _ => unreachable!(),
+            };
+            match result {
+                Some(val) => self.write(reg, val),
+                None => return Err(format!("line {}: overflow in register '{}'",
+                                           program.lines[self.pc], program.registers.name(reg))),
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn write(&mut self, reg: usize, val: isize) {
+        if val > self.highest_seen { self.highest_seen = val }
+        self.registers[reg] = val;
+        self.pc += 1;
+    }
+}
+
+/// Accumulates instructions, patching forward jumps once their targets
+/// are known.
+#[derive(Default)]
+struct Builder {
+    instrs: Vec<Instr>,
+    lines: Vec<usize>,
+    registers: Interner,
+    /// For each label, the instruction it points to once placed.
+    labels: Vec<Option<usize>>,
+    /// Branches whose target is a label: (instruction, label).
+    label_branches: Vec<(usize, usize)>,
+    /// The first instruction of each source statement.
+    statement_starts: Vec<usize>,
+    /// Jumps whose target is a statement index: (instruction, statement).
+    statement_jumps: Vec<(usize, usize)>,
+}
+
+fn operand(val: &Value) -> Operand {
+    match *val {
+        Value::Register(idx) => Operand::Register(idx),
+        Value::Literal(val) => Operand::Literal(val),
+    }
+}
+
+impl Builder {
+    fn new_label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place_label(&mut self, label: usize) {
+        self.labels[label] = Some(self.instrs.len());
+    }
+
+    fn branch(&mut self, lhs: &Value, op: Op, rhs: &Value, label: usize) {
+        let (lhs, rhs) = (operand(lhs), operand(rhs));
+        self.label_branches.push((self.instrs.len(), label));
+        self.instrs.push(Instr::Branch(lhs, op, rhs, label));
+    }
+
+    /// Emits code that falls through if `cond` holds and jumps to
+    /// `label` otherwise.
+    fn branch_unless(&mut self, cond: &Condition, label: usize) {
+        match *cond {
+            Condition::Always => (),
+            Condition::Compare(ref lhs, ref op, ref rhs) => self.branch(lhs, op.negate(), rhs, label),
+            Condition::And(ref lhs, ref rhs) => {
+                self.branch_unless(lhs, label);
+                self.branch_unless(rhs, label);
+            }
+            Condition::Or(ref lhs, ref rhs) => {
+                let pass = self.new_label();
+                self.branch_if(lhs, pass);
+                self.branch_unless(rhs, label);
+                self.place_label(pass);
+            }
+        }
+    }
+
+    /// Emits code that jumps to `label` if `cond` holds and falls
+    /// through otherwise.
+    fn branch_if(&mut self, cond: &Condition, label: usize) {
+        match *cond {
+            Condition::Always => {
+                self.label_branches.push((self.instrs.len(), label));
+                self.instrs.push(Instr::Jump(label));
+            }
+            Condition::Compare(ref lhs, ref op, ref rhs) => self.branch(lhs, op.clone(), rhs, label),
+            Condition::Or(ref lhs, ref rhs) => {
+                self.branch_if(lhs, label);
+                self.branch_if(rhs, label);
+            }
+            Condition::And(ref lhs, ref rhs) => {
+                let fail = self.new_label();
+                self.branch_unless(lhs, fail);
+                self.branch_if(rhs, label);
+                self.place_label(fail);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Compiled {
+        let end = self.instrs.len();
+        for (instr, label) in self.label_branches {
+            let target = self.labels[label].unwrap();
+            set_target(&mut self.instrs[instr], target);
+        }
+        for (instr, statement) in self.statement_jumps {
+            let target = self.statement_starts.get(statement).cloned().unwrap_or(end);
+            set_target(&mut self.instrs[instr], target);
+        }
+        Compiled { instrs: self.instrs, lines: self.lines, registers: self.registers }
+    }
+}
+
+fn set_target(instr: &mut Instr, new_target: usize) {
+    match *instr {
+        Instr::Jump(ref mut target) | Instr::Branch(_, _, _, ref mut target) => *target = new_target,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interp::{self, Machine};
+
+    fn run_source(source: &str, overflow: Overflow) -> Result<(Compiled, Executor), String> {
+        let program = source.parse::<interp::Program>()?;
+        let compiled = Compiled::from_program(&program);
+        let mut executor = compiled.executor();
+        executor.run(&compiled, overflow, None)?;
+        Ok((compiled, executor))
+    }
+
+    fn get(compiled: &Compiled, executor: &Executor, name: &str) -> isize {
+        compiled.registers.indices.get(name).map(|&idx| executor.registers[idx]).unwrap_or(0)
+    }
+
+    #[test]
+    fn matches_interpreter() {
+        let src = "n set 10
+            loop: a inc n if (n > 3 and n != 7) or a == 0
+            b mul 2 if n < 3 or n == 5 and b == 0
+            b set 1 if b == 0
+            n dec 1
+            jmp loop if n > 0";
+        let program = src.parse::<interp::Program>().unwrap();
+        let mut machine = Machine::new();
+        machine.run(&program, None).unwrap();
+        let (compiled, executor) = run_source(src, Overflow::Checked).unwrap();
+        for reg in &["a", "b", "n"] {
+            assert_eq!(get(&compiled, &executor, reg), machine.get(reg), "register {}", reg);
+        }
+        assert_eq!(executor.highest_seen, machine.highest_seen);
+    }
+
+    #[test]
+    fn overflow() {
+        let src = "a set 2\nloop: a mul a if a > 0 or b == 0\njmp loop";
+        let err = run_source(src, Overflow::Checked).unwrap_err();
+        assert_eq!(err, "line 2: overflow in register 'a'");
+
+        let src = "a set 2\na mul a\na mul a\na mul a\na mul a\na mul a\na mul a\na mul a";
+        let (compiled, executor) = run_source(src, Overflow::Wrapping).unwrap();
+        assert_eq!(get(&compiled, &executor, "a"), 0);
+    }
+}
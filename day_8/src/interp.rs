@@ -0,0 +1,454 @@
+//! An interpreter for an extended version of the register language.
+//!
+//! On top of the puzzle's `reg inc|dec n if reg op n` lines, this supports:
+//!
+//! - registers or literals on either side of a comparison, or as an operand
+//! - `and`, `or` and parentheses in conditions (`and` binds tighter)
+//! - `set` and `mul` alongside `inc` and `dec`
+//! - labels (`name:`, on their own line or before a statement) and
+//!   `jmp name`, which may also be guarded by a condition
+//! - blank lines and `#` comments
+//!
+//! The condition is optional; an unguarded statement always runs.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::Op;
+use compile::Interner;
+
+pub type Register = String;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A register, by its index in `Program::registers`.
+    Register(usize),
+    Literal(isize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Always,
+    Compare(Value, Op, Value),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Inc(usize, Value),
+    Dec(usize, Value),
+    Set(usize, Value),
+    Mul(usize, Value),
+    /// Jump to the statement index a label resolved to.
+    Jump(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub action: Action,
+    pub condition: Condition,
+    /// The 1-based line this statement was parsed from.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+    pub labels: HashMap<String, usize>,
+    /// Every register the program mentions, interned while parsing.
+    pub registers: Interner,
+}
+
+/// A single register write: the index of the statement that made it,
+/// and the register's value before and after.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Change {
+    pub index: usize,
+    pub old: isize,
+    pub new: isize,
+}
+
+/// Every write made to each register, in execution order.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    changes: HashMap<Register, Vec<Change>>,
+}
+
+/// The register file and program counter for a running `Program`.
+#[derive(Debug, Clone, Default)]
+pub struct Machine {
+    pub registers: HashMap<Register, isize>,
+    pub pc: usize,
+    /// The highest value written to any register so far.
+    pub highest_seen: isize,
+    pub steps: usize,
+    /// Only recorded if the machine was created with `with_history`.
+    pub history: Option<History>,
+}
+
+impl Value {
+    fn get(&self, names: &Interner, registers: &HashMap<Register, isize>) -> isize {
+        match *self {
+            Value::Register(idx) => *registers.get(names.name(idx)).unwrap_or(&0),
+            Value::Literal(val) => val,
+        }
+    }
+}
+
+impl Condition {
+    pub fn eval(&self, names: &Interner, registers: &HashMap<Register, isize>) -> bool {
+        match *self {
+            Condition::Always => true,
+            Condition::Compare(ref lhs, ref op, ref rhs) =>
+                op.compare(lhs.get(names, registers), rhs.get(names, registers)),
+            Condition::And(ref lhs, ref rhs) =>
+                lhs.eval(names, registers) && rhs.eval(names, registers),
+            Condition::Or(ref lhs, ref rhs) =>
+                lhs.eval(names, registers) || rhs.eval(names, registers),
+        }
+    }
+}
+
+impl History {
+    pub fn changes(&self, reg: &str) -> &[Change] {
+        self.changes.get(reg).map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    /// The first write that gave `reg` its highest value, if it was
+    /// ever written.
+    pub fn max_of(&self, reg: &str) -> Option<Change> {
+        // max_by_key returns the last maximum; we want the first.
+        self.changes(reg).iter().rev().max_by_key(|c| c.new).cloned()
+    }
+
+    fn record(&mut self, reg: &str, change: Change) {
+        if !self.changes.contains_key(reg) {
+            self.changes.insert(reg.to_owned(), Vec::new());
+        }
+        self.changes.get_mut(reg).unwrap().push(change);
+    }
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Machine::default()
+    }
+
+    pub fn with_history() -> Self {
+        Machine { history: Some(History::default()), ..Machine::default() }
+    }
+
+    pub fn get(&self, reg: &str) -> isize {
+        *self.registers.get(reg).unwrap_or(&0)
+    }
+
+    pub fn is_halted(&self, program: &Program) -> bool {
+        self.pc >= program.statements.len()
+    }
+
+    /// Executes the statement at the program counter. Returns `Ok(false)`
+    /// if the program had already halted. If the arithmetic overflows,
+    /// returns an error naming the source line and leaves the machine
+    /// as it was.
+    pub fn step(&mut self, program: &Program) -> Result<bool, String> {
+        let statement = match program.statements.get(self.pc) {
+            Some(s) => s,
+            None => return Ok(false),
+        };
+        let index = self.pc;
+        let names = &program.registers;
+        if !statement.condition.eval(names, &self.registers) {
+            self.steps += 1;
+            self.pc += 1;
+            return Ok(true)
+        }
+
+        let (reg, new_val) = match statement.action {
+            Action::Jump(target) => {
+                self.steps += 1;
+                self.pc = target;
+                return Ok(true)
+            }
+            Action::Inc(reg, ref val) | Action::Dec(reg, ref val) | Action::Mul(reg, ref val) |
+            Action::Set(reg, ref val) => {
+                let (reg, val) = (names.name(reg), val.get(names, &self.registers));
+                (reg, match statement.action {
+                    Action::Inc(..) => self.get(reg).checked_add(val),
+                    Action::Dec(..) => self.get(reg).checked_sub(val),
+                    Action::Mul(..) => self.get(reg).checked_mul(val),
+                    _ => Some(val),
+                })
+            }
+        };
+        let new_val = new_val
+            .ok_or_else(|| format!("line {}: overflow in register '{}'", statement.line, reg))?;
+        self.steps += 1;
+        self.pc += 1;
+        if new_val > self.highest_seen { self.highest_seen = new_val }
+        let old = self.registers.insert(reg.to_owned(), new_val).unwrap_or(0);
+        if let Some(ref mut history) = self.history {
+            history.record(reg, Change { index, old, new: new_val });
+        }
+        Ok(true)
+    }
+
+    /// Runs until the program halts, or returns an error if it is still
+    /// running after `max_steps` statements or its arithmetic overflows.
+    pub fn run(&mut self, program: &Program, max_steps: Option<usize>) -> Result<(), String> {
+        while self.step(program)? {
+            if let Some(max) = max_steps {
+                if self.steps >= max && !self.is_halted(program) {
+                    return Err(format!("no halt after {} steps (pc = {})", self.steps, self.pc))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The largest value currently held in any register.
+    pub fn max_register(&self) -> isize {
+        *self.registers.values().max().unwrap_or(&0)
+    }
+}
+
+impl FromStr for Program {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // labels can be used before they're declared, so resolve jumps
+        // in a second pass.
+        let mut labels = HashMap::new();
+        let mut registers = Interner::new();
+        let mut pending = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let mut line = line.split('#').next().unwrap().trim();
+            if let Some(colon) = line.find(':') {
+                let label = line[..colon].trim();
+                if !is_identifier(label) {
+                    return Err(format!("line {}: invalid label '{}'", i + 1, label))
+                }
+                if labels.insert(label.to_owned(), pending.len()).is_some() {
+                    return Err(format!("line {}: duplicate label '{}'", i + 1, label))
+                }
+                line = line[colon + 1..].trim();
+            }
+            if !line.is_empty() {
+                let tokens = tokenize(line);
+                let parsed = parse_statement(&tokens, &mut registers)
+                    .map_err(|e| format!("line {}: {}", i + 1, e))?;
+                pending.push((i + 1, parsed));
+            }
+        }
+
+        let mut statements = Vec::with_capacity(pending.len());
+        for (line, (action, condition)) in pending {
+            let action = match action {
+                RawAction::Jump(label) => match labels.get(&label) {
+                    Some(&idx) => Action::Jump(idx),
+                    None => return Err(format!("line {}: unknown label '{}'", line, label)),
+                },
+                RawAction::Other(action) => action,
+            };
+            statements.push(Statement { action, condition, line });
+        }
+        Ok(Program { statements, labels, registers })
+    }
+}
+
+enum RawAction {
+    Jump(String),
+    Other(Action),
+}
+
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    for word in line.split_whitespace() {
+        let mut start = 0;
+        for (i, c) in word.char_indices() {
+            if c == '(' || c == ')' {
+                if start < i { tokens.push(&word[start..i]) }
+                tokens.push(&word[i..i + 1]);
+                start = i + 1;
+            }
+        }
+        if start < word.len() { tokens.push(&word[start..]) }
+    }
+    tokens
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !["if", "and", "or", "jmp", "inc", "dec", "set", "mul"].contains(&s)
+}
+
+fn parse_value(token: Option<&&str>, registers: &mut Interner) -> Result<Value, String> {
+    match token {
+        None => Err("expected a register or number, found end of line".into()),
+        Some(t) => match t.parse::<isize>() {
+            Ok(val) => Ok(Value::Literal(val)),
+            Err(_) if is_identifier(t) => Ok(Value::Register(registers.intern(t))),
+            Err(_) => Err(format!("expected a register or number, found '{}'", t)),
+        },
+    }
+}
+
+fn parse_statement(tokens: &[&str], registers: &mut Interner) -> Result<(RawAction, Condition), String> {
+    let (action, rest) = if tokens[0] == "jmp" {
+        match tokens.get(1) {
+            Some(label) if is_identifier(label) => (RawAction::Jump((*label).into()), &tokens[2..]),
+            _ => return Err("'jmp' expects a label".into()),
+        }
+    } else {
+        let reg = match parse_value(tokens.first(), registers)? {
+            Value::Register(reg) => reg,
+            Value::Literal(n) => return Err(format!("cannot assign to literal {}", n)),
+        };
+        let val = parse_value(tokens.get(2), registers)?;
+        let action = match tokens.get(1) {
+            Some(&"inc") => Action::Inc(reg, val),
+            Some(&"dec") => Action::Dec(reg, val),
+            Some(&"set") => Action::Set(reg, val),
+            Some(&"mul") => Action::Mul(reg, val),
+            Some(other) => return Err(format!("unknown instruction '{}'", other)),
+            None => return Err("expected an instruction".into()),
+        };
+        (RawAction::Other(action), &tokens[3..])
+    };
+
+    if rest.is_empty() {
+        return Ok((action, Condition::Always))
+    }
+    if rest[0] != "if" {
+        return Err(format!("expected 'if', found '{}'", rest[0]))
+    }
+    let mut pos = 1;
+    let condition = parse_or(rest, &mut pos, registers)?;
+    if pos < rest.len() {
+        return Err(format!("unexpected '{}' after condition", rest[pos]))
+    }
+    Ok((action, condition))
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize, registers: &mut Interner) -> Result<Condition, String> {
+    let mut lhs = parse_and(tokens, pos, registers)?;
+    while tokens.get(*pos) == Some(&"or") {
+        *pos += 1;
+        let rhs = parse_and(tokens, pos, registers)?;
+        lhs = Condition::Or(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize, registers: &mut Interner) -> Result<Condition, String> {
+    let mut lhs = parse_comparison(tokens, pos, registers)?;
+    while tokens.get(*pos) == Some(&"and") {
+        *pos += 1;
+        let rhs = parse_comparison(tokens, pos, registers)?;
+        lhs = Condition::And(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_comparison(tokens: &[&str], pos: &mut usize, registers: &mut Interner)
+    -> Result<Condition, String>
+{
+    if tokens.get(*pos) == Some(&"(") {
+        *pos += 1;
+        let inner = parse_or(tokens, pos, registers)?;
+        if tokens.get(*pos) != Some(&")") {
+            return Err("expected ')'".into())
+        }
+        *pos += 1;
+        return Ok(inner)
+    }
+    let lhs = parse_value(tokens.get(*pos), registers)?;
+    let op = match tokens.get(*pos + 1) {
+        Some(&op) if ["==", "!=", ">", ">=", "<", "<="].contains(&op) => Op::from(op),
+        Some(other) => return Err(format!("expected a comparison, found '{}'", other)),
+        None => return Err("expected a comparison, found end of line".into()),
+    };
+    let rhs = parse_value(tokens.get(*pos + 2), registers)?;
+    *pos += 3;
+    Ok(Condition::Compare(lhs, op, rhs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_conditions() {
+        let prog = "a inc b if (b > c or c == 1) and d <= -3".parse::<Program>().unwrap();
+        // registers are numbered in order of first mention
+        let reg = |s: &str| Value::Register((0..4).position(|i| prog.registers.name(i) == s).unwrap());
+        let expected = Condition::And(
+            Box::new(Condition::Or(
+                Box::new(Condition::Compare(reg("b"), Op::GreaterThan, reg("c"))),
+                Box::new(Condition::Compare(reg("c"), Op::Equal, Value::Literal(1))))),
+            Box::new(Condition::Compare(reg("d"), Op::LessThanOrEqual, Value::Literal(-3))));
+        assert_eq!(prog.registers.len(), 4);
+        assert_eq!(prog.statements[0].action, Action::Inc(0, reg("b")));
+        assert_eq!(prog.statements[0].condition, expected);
+
+        assert!("a inc 1 if".parse::<Program>().is_err());
+        assert!("jmp nowhere".parse::<Program>().unwrap_err().contains("unknown label"));
+        assert!("a inc 1\n5 set 2".parse::<Program>().unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn run_loop() {
+        let src = "# multiply by repeated addition
+            n set 6
+            loop: total inc 7
+            n dec 1
+            jmp loop if n > 0 and total < 1000
+            done set 1 if total == 42 or n < 0";
+        let prog = src.parse::<Program>().unwrap();
+        let mut machine = Machine::new();
+        machine.run(&prog, Some(100)).unwrap();
+        assert_eq!(machine.get("total"), 42);
+        assert_eq!(machine.get("done"), 1);
+        assert_eq!(machine.highest_seen, 42);
+
+        let forever = "top: jmp top".parse::<Program>().unwrap();
+        assert!(Machine::new().run(&forever, Some(100)).is_err());
+    }
+
+    #[test]
+    fn overflow() {
+        let prog = "a set 9223372036854775807\n\na inc 1".parse::<Program>().unwrap();
+        let mut machine = Machine::new();
+        assert_eq!(machine.run(&prog, None), Err("line 3: overflow in register 'a'".into()));
+        // the failed statement can be inspected, or retried after an edit
+        assert_eq!(machine.pc, 1);
+        assert_eq!(machine.get("a"), isize::MAX);
+
+        let prog = "a set -2\nloop: a mul a\njmp loop".parse::<Program>().unwrap();
+        assert!(Machine::new().run(&prog, None).unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn history() {
+        let src = "c inc 5\nc dec 2\nc inc 2\nc dec 9\nb set 1 if c < 0";
+        let prog = src.parse::<Program>().unwrap();
+        let mut machine = Machine::with_history();
+        machine.run(&prog, None).unwrap();
+        let history = machine.history.unwrap();
+        assert_eq!(history.changes("c").len(), 4);
+        assert_eq!(history.changes("c")[3], Change { index: 3, old: 5, new: -4 });
+        // c is 5 after both statement 0 and statement 2
+        assert_eq!(history.max_of("c"), Some(Change { index: 0, old: 0, new: 5 }));
+        assert_eq!(history.max_of("nope"), None);
+    }
+
+    #[test]
+    fn matches_part_both() {
+        let src = "b inc 5 if a > 1\na inc 1 if b < 5\nc dec -10 if a >= 1\nc inc -20 if c == 10";
+        let prog = src.parse::<Program>().unwrap();
+        let mut machine = Machine::new();
+        machine.run(&prog, None).unwrap();
+        assert_eq!((machine.max_register(), machine.highest_seen), (1, 10));
+    }
+}
@@ -1,34 +1,135 @@
-use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
 
-type Register = String;
+mod compile;
+mod interp;
+mod repl;
+
+use compile::{Interner, Overflow};
+
+/// An interned register index.
+type Register = usize;
 type Instruction = (Register, isize, Condition);
 
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut overflow = Overflow::Checked;
+    match args.first().map(String::as_str) {
+        Some("--repl") => return start_repl(args.get(1).expect("usage: day_8 --repl <file>")),
+        Some("--wrapping") => match args.get(1) {
+            Some(path) => return run_script(path, Overflow::Wrapping),
+            // no script, so solve the puzzle input with wrapping arithmetic
+            None => overflow = Overflow::Wrapping,
+        },
+        Some("--interpret") => return interpret_script(args.get(1).expect("usage: day_8 --interpret <file>")),
+        Some(path) => return run_script(path, Overflow::Checked),
+        None => (),
+    }
+
     let input = include_str!("../input.txt").trim().lines().collect::<Vec<_>>();
-    let instructions = input.iter().map(|line| parse_line(&line)).collect::<Vec<_>>();
-    let result = part_both(&instructions);
+    let mut registers = Interner::new();
+    let instructions = input.iter().map(|line| parse_line(line, &mut registers)).collect::<Vec<_>>();
+    let result = match part_both(&instructions, &registers, overflow) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("input.txt: {}", e);
+            std::process::exit(1);
+        }
+    };
     println!("part one: {}", result.0);
     println!("part two: {}", result.1);
+}
+
+/// Loads a program into the interactive debugger.
+fn start_repl(path: &str) {
+    let source = fs::read_to_string(path).expect("failed to read script");
+    let mut repl = match repl::Repl::new(source) {
+        Ok(repl) => repl,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let stdin = io::stdin();
+    repl.run(stdin.lock(), io::stdout()).expect("io error");
+}
+
+fn load_script(path: &str) -> interp::Program {
+    let source = fs::read_to_string(path).expect("failed to read script");
+    match source.parse::<interp::Program>() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
 
+/// Runs a script with the tree-walking interpreter, which is slower
+/// but handy for cross-checking the compiled version.
+fn interpret_script(path: &str) {
+    let program = load_script(path);
+    let mut machine = interp::Machine::new();
+    if let Err(e) = machine.run(&program, Some(100_000_000)) {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    }
+    let mut registers = machine.registers.iter().collect::<Vec<_>>();
+    registers.sort();
+    for (reg, val) in registers {
+        println!("{} = {}", reg, val);
+    }
+    println!("largest value: {}", machine.max_register());
+    println!("highest seen: {}", machine.highest_seen);
 }
 
-/// Returns both the max final value and the max value at any point.
-fn part_both(instructions: &[Instruction]) -> (isize, isize) {
-    // use a hashmap for registers so we can keep them as strings
-    let mut registers = HashMap::<Register, isize>::new();
+/// Compiles and runs a script in the extended language, printing the
+/// final registers.
+fn run_script(path: &str, overflow: Overflow) {
+    let program = load_script(path);
+    let compiled = compile::Compiled::from_program(&program);
+    let mut executor = compiled.executor();
+    if let Err(e) = executor.run(&compiled, overflow, Some(100_000_000)) {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    }
+    let mut registers = (0..compiled.registers.len())
+        .map(|idx| (compiled.registers.name(idx), executor.registers[idx]))
+        .collect::<Vec<_>>();
+    registers.sort();
+    for (reg, val) in registers {
+        println!("{} = {}", reg, val);
+    }
+    println!("largest value: {}", executor.registers.iter().max().unwrap_or(&0));
+    println!("highest seen: {}", executor.highest_seen);
+}
+
+/// Returns both the max final value and the max value at any point, or
+/// an error naming the line that overflowed if `overflow` is `Checked`.
+fn part_both(instructions: &[Instruction], names: &Interner, overflow: Overflow)
+    -> Result<(isize, isize), String>
+{
+    // registers were interned at parse time, so they're just indices
+    let mut registers = vec![0isize; names.len()];
     let mut highest_seen = 0;
 
-    for instruction in instructions {
+    for (i, instruction) in instructions.iter().enumerate() {
         let condition = &instruction.2;
-        let lhs = *registers.get(&condition.register).unwrap_or(&0);
+        let lhs = registers[condition.register];
         if condition.comparison.compare(lhs, condition.other_side) {
-            let cur_val = *registers.get(&instruction.0).unwrap_or(&0);
-            let new_val = cur_val + instruction.1;
+            let old = registers[instruction.0];
+            let new_val = match overflow {
+                Overflow::Wrapping => old.wrapping_add(instruction.1),
+                Overflow::Checked => old.checked_add(instruction.1).ok_or_else(|| {
+                    format!("line {}: overflow in register '{}'", i + 1, names.name(instruction.0))
+                })?,
+            };
             if new_val > highest_seen { highest_seen = new_val }
-            registers.insert(instruction.0.clone(), new_val);
+            registers[instruction.0] = new_val;
         }
     }
-    (*registers.values().max().unwrap_or(&0), highest_seen)
+    Ok((*registers.iter().max().unwrap_or(&0), highest_seen))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -66,6 +167,18 @@ impl Op {
             Op::LessThanOrEqual => lhs <= rhs,
         }
     }
+
+    /// The comparison that holds exactly when this one doesn't.
+    fn negate(&self) -> Op {
+        match *self {
+            Op::Equal => Op::NotEqual,
+            Op::NotEqual => Op::Equal,
+            Op::GreaterThan => Op::LessThanOrEqual,
+            Op::GreaterThanOrEqual => Op::LessThan,
+            Op::LessThan => Op::GreaterThanOrEqual,
+            Op::LessThanOrEqual => Op::GreaterThan,
+        }
+    }
 }
 
 struct Condition {
@@ -74,21 +187,22 @@ struct Condition {
     other_side: isize,
 }
 
-/// Parses an input line into a (name, weight, [child_name]) tuple.
-fn parse_line(line: &'static str) -> (Register, isize, Condition) {
+/// Parses an input line into a (register, delta, condition) tuple,
+/// interning register names as it goes.
+fn parse_line(line: &str, registers: &mut Interner) -> Instruction {
     let mut iter = line.split_whitespace();
     let register = iter.next().unwrap();
     let inc = iter.next().unwrap();
-    let mut val = isize::from_str_radix(iter.next().unwrap(), 10).unwrap();
+    let mut val = iter.next().unwrap().parse::<isize>().unwrap();
     if inc == "dec" { val *= -1 }
 
     // discard the if field
     assert_eq!(iter.next(), Some("if"));
     let cond_reg = iter.next().unwrap();
     let cond_op = Op::from(iter.next().unwrap());
-    let cond_num = isize::from_str_radix(iter.next().unwrap(), 10).unwrap();
-    let cond = Condition { register: cond_reg.into(), comparison: cond_op, other_side: cond_num };
-    (register.into(), val, cond)
+    let cond_num = iter.next().unwrap().parse::<isize>().unwrap();
+    let cond = Condition { register: registers.intern(cond_reg), comparison: cond_op, other_side: cond_num };
+    (registers.intern(register), val, cond)
 }
 
 
@@ -98,11 +212,23 @@ mod tests {
     #[test]
     fn test_parse() {
         let inp = "c dec -10 if a >= 1";
-        let result = parse_line(&inp);
-        assert_eq!(result.0, String::from("c"));
+        let mut registers = Interner::new();
+        let result = parse_line(inp, &mut registers);
+        assert_eq!(registers.name(result.0), "c");
         assert_eq!(result.1, 10);
-        assert_eq!(result.2.register, String::from("a"));
+        assert_eq!(registers.name(result.2.register), "a");
         assert_eq!(result.2.comparison, Op::GreaterThanOrEqual);
         assert_eq!(result.2.other_side, 1);
     }
+
+    #[test]
+    fn overflow() {
+        let src = ["a inc 9223372036854775807 if b == 0", "b inc 1 if a > 0", "a inc 1 if b == 1"];
+        let mut registers = Interner::new();
+        let instructions = src.iter().map(|line| parse_line(line, &mut registers)).collect::<Vec<_>>();
+        assert_eq!(part_both(&instructions, &registers, Overflow::Checked),
+                   Err("line 3: overflow in register 'a'".into()));
+        assert_eq!(part_both(&instructions, &registers, Overflow::Wrapping),
+                   Ok((1, isize::MAX)));
+    }
 }
@@ -0,0 +1,216 @@
+//! An interactive debugger for register programs.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use interp::{Machine, Program};
+
+static HELP: &str = "\
+commands:
+  s, step [n]         execute the next n statements (default 1)
+  r, run              run until a breakpoint or the end of the program, pausing
+                      every million steps
+  b, break <line|label>
+                      break before the statement on a source line or label
+  d, delete <line|label>
+                      remove a breakpoint
+  l, list             show the next statement
+  p, print [reg]      print one register, or all of them
+  set <reg> <value>   change a register's value
+  h, history <reg>    list every write to a register
+  max <reg>           show when a register reached its highest value
+  reset               restart the program, keeping breakpoints
+  q, quit             exit";
+
+/// How many statements `run` executes before handing back control, so a
+/// program that never halts doesn't take the prompt with it.
+const RUN_LIMIT: usize = 1_000_000;
+
+pub struct Repl {
+    source: String,
+    program: Program,
+    machine: Machine,
+    /// Statement indices to stop before.
+    breakpoints: BTreeSet<usize>,
+    run_limit: usize,
+}
+
+impl Repl {
+    pub fn new(source: String) -> Result<Self, String> {
+        let program = source.parse::<Program>()?;
+        Ok(Repl {
+            source,
+            program,
+            machine: Machine::with_history(),
+            breakpoints: BTreeSet::new(),
+            run_limit: RUN_LIMIT,
+        })
+    }
+
+    /// Reads commands from `input` until it is exhausted or a `quit`.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if ["q", "quit"].contains(&line.trim()) {
+                break
+            }
+            match self.execute(&line) {
+                Ok(ref msg) if msg.is_empty() => (),
+                Ok(msg) => writeln!(output, "{}", msg)?,
+                Err(e) => writeln!(output, "error: {}", e)?,
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Executes a single command, returning the text to display.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(c) => c,
+            None => return Ok(String::new()),
+        };
+        let arg = words.next();
+        match command {
+            "s" | "step" => {
+                let count = match arg {
+                    Some(n) => n.parse::<usize>().map_err(|e| format!("{}", e))?,
+                    None => 1,
+                };
+                for _ in 0..count {
+                    if !self.machine.step(&self.program)? { break }
+                }
+                Ok(self.describe_pc())
+            }
+            "r" | "run" => {
+                // always make progress, even if we're sitting on a breakpoint
+                let mut steps = 0;
+                while self.machine.step(&self.program)? {
+                    if self.breakpoints.contains(&self.machine.pc) {
+                        return Ok(format!("breakpoint\n{}", self.describe_pc()))
+                    }
+                    steps += 1;
+                    if steps == self.run_limit {
+                        return Ok(format!("paused after {} steps\n{}", steps, self.describe_pc()))
+                    }
+                }
+                Ok(self.describe_pc())
+            }
+            "b" | "break" => {
+                let idx = self.resolve_location(arg)?;
+                self.breakpoints.insert(idx);
+                Ok(format!("breakpoint at line {}", self.program.statements[idx].line))
+            }
+            "d" | "delete" => {
+                let idx = self.resolve_location(arg)?;
+                if self.breakpoints.remove(&idx) {
+                    Ok(String::new())
+                } else {
+                    Err("no breakpoint there".into())
+                }
+            }
+            "l" | "list" => Ok(self.describe_pc()),
+            "p" | "print" => match arg {
+                Some(reg) => Ok(format!("{} = {}", reg, self.machine.get(reg))),
+                None => {
+                    let mut registers = self.machine.registers.iter().collect::<Vec<_>>();
+                    registers.sort();
+                    Ok(registers.iter()
+                       .map(|&(reg, val)| format!("{} = {}", reg, val))
+                       .collect::<Vec<_>>()
+                       .join("\n"))
+                }
+            },
+            "set" => {
+                let reg = arg.ok_or("usage: set <reg> <value>")?;
+                let val = words.next().ok_or("usage: set <reg> <value>")?
+                    .parse::<isize>().map_err(|e| format!("{}", e))?;
+                self.machine.registers.insert(reg.to_owned(), val);
+                Ok(String::new())
+            }
+            "h" | "history" => {
+                let reg = arg.ok_or("usage: history <reg>")?;
+                let history = self.machine.history.as_ref().unwrap();
+                Ok(history.changes(reg).iter()
+                   .map(|c| format!("line {}: {} -> {}",
+                                    self.program.statements[c.index].line, c.old, c.new))
+                   .collect::<Vec<_>>()
+                   .join("\n"))
+            }
+            "max" => {
+                let reg = arg.ok_or("usage: max <reg>")?;
+                match self.machine.history.as_ref().unwrap().max_of(reg) {
+                    Some(c) => Ok(format!("{} reached {} at line {}",
+                                          reg, c.new, self.program.statements[c.index].line)),
+                    None => Ok(format!("{} was never written", reg)),
+                }
+            }
+            "reset" => {
+                self.machine = Machine::with_history();
+                Ok(self.describe_pc())
+            }
+            "help" | "?" => Ok(HELP.to_owned()),
+            other => Err(format!("unknown command '{}' (try 'help')", other)),
+        }
+    }
+
+    /// Maps a source line number or label to a statement index.
+    fn resolve_location(&self, arg: Option<&str>) -> Result<usize, String> {
+        let arg = arg.ok_or("expected a line number or label")?;
+        if let Some(&idx) = self.program.labels.get(arg) {
+            if idx < self.program.statements.len() {
+                return Ok(idx)
+            }
+        }
+        let line = arg.parse::<usize>().map_err(|_| format!("no label '{}'", arg))?;
+        self.program.statements.iter()
+            .position(|s| s.line == line)
+            .ok_or_else(|| format!("no statement on line {}", line))
+    }
+
+    fn describe_pc(&self) -> String {
+        match self.program.statements.get(self.machine.pc) {
+            Some(s) => format!("{:>4}: {}", s.line, self.source.lines().nth(s.line - 1).unwrap().trim()),
+            None => format!("halted after {} steps", self.machine.steps),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breakpoints_and_edits() {
+        let src = "n set 3\nloop: t inc 2\nn dec 1\njmp loop if n > 0";
+        let mut repl = Repl::new(src.into()).unwrap();
+        assert_eq!(repl.execute("break loop").unwrap(), "breakpoint at line 2");
+        assert_eq!(repl.execute("run").unwrap(), "breakpoint\n   2: loop: t inc 2");
+        assert_eq!(repl.execute("step 2").unwrap(), "   4: jmp loop if n > 0");
+        assert_eq!(repl.execute("p n").unwrap(), "n = 2");
+        repl.execute("set n 1").unwrap();
+        repl.execute("delete loop").unwrap();
+        assert_eq!(repl.execute("run").unwrap(), "halted after 7 steps");
+        assert_eq!(repl.execute("print").unwrap(), "n = 0\nt = 4");
+        assert_eq!(repl.execute("max n").unwrap(), "n reached 3 at line 1");
+        assert_eq!(repl.execute("history t").unwrap(), "line 2: 0 -> 2\nline 2: 2 -> 4");
+        assert!(repl.execute("break 9").is_err());
+    }
+
+    #[test]
+    fn runaway() {
+        let mut repl = Repl::new("n inc 1\ntop: jmp top".into()).unwrap();
+        repl.run_limit = 100;
+        assert_eq!(repl.execute("run").unwrap(), "paused after 100 steps\n   2: top: jmp top");
+        assert_eq!(repl.execute("run").unwrap(), "paused after 100 steps\n   2: top: jmp top");
+        assert_eq!(repl.execute("p n").unwrap(), "n = 1");
+
+        let mut repl = Repl::new("a set 9223372036854775807\na inc 1".into()).unwrap();
+        assert_eq!(repl.execute("run"), Err("line 2: overflow in register 'a'".into()));
+        assert_eq!(repl.execute("list").unwrap(), "   2: a inc 1");
+    }
+}
@@ -0,0 +1,167 @@
+//! Builds a tree of groups and garbage from a stream, and prints it back
+//! out in canonical form.
+
+use stream::{Event, Mode, ParseError, Parser};
+
+/// A half-open range of byte offsets into the input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Group {
+        span: Span,
+        children: Vec<Node>,
+    },
+    Garbage {
+        span: Span,
+        /// The uncancelled bytes between the `<` and `>`. Cancelled bytes
+        /// and the `!`s that cancelled them are dropped.
+        contents: Vec<u8>,
+    },
+}
+
+/// Assembles `Node`s from parser events.
+#[derive(Debug, Default)]
+pub struct TreeBuilder {
+    /// Open groups: their start offset and the children seen so far.
+    open: Vec<(usize, Vec<Node>)>,
+    garbage: Option<(usize, Vec<u8>)>,
+    roots: Vec<Node>,
+}
+
+impl TreeBuilder {
+    pub fn new() -> Self {
+        TreeBuilder::default()
+    }
+
+    pub fn event(&mut self, offset: usize, event: Event) {
+        match event {
+            Event::GroupOpen => self.open.push((offset, Vec::new())),
+            Event::GroupClose => {
+                let (start, children) = self.open.pop().expect("unbalanced group close");
+                let span = Span { start, end: offset + 1 };
+                self.push(Node::Group { span, children });
+            }
+            Event::GarbageStart => self.garbage = Some((offset, Vec::new())),
+            Event::GarbageByte(b) => self.garbage.as_mut().unwrap().1.push(b),
+            Event::GarbageEnd => {
+                let (start, contents) = self.garbage.take().unwrap();
+                let span = Span { start, end: offset + 1 };
+                self.push(Node::Garbage { span, contents });
+            }
+            Event::Escape(_) => (),
+        }
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.open.last_mut() {
+            Some(&mut (_, ref mut children)) => children.push(node),
+            None => self.roots.push(node),
+        }
+    }
+
+    /// Returns the completed top-level groups.
+    pub fn finish(self) -> Vec<Node> {
+        self.roots
+    }
+}
+
+/// Parses a complete stream into its top-level groups.
+pub fn parse(input: &[u8]) -> Result<Vec<Node>, ParseError> {
+    let mut parser = Parser::new();
+    let mut builder = TreeBuilder::new();
+    parser.feed(input, |offset, event| builder.event(offset, event))?;
+    parser.finish(|offset, event| builder.event(offset, event))?;
+    Ok(builder.finish())
+}
+
+/// Parses as much of a stream as possible, returning whatever tree could
+/// be recovered along with every error encountered.
+pub fn parse_lenient(input: &[u8]) -> (Vec<Node>, Vec<ParseError>) {
+    let mut parser = Parser::with_mode(Mode::Lenient);
+    let mut builder = TreeBuilder::new();
+    // neither of these can fail in lenient mode
+    parser.feed(input, |offset, event| builder.event(offset, event)).unwrap();
+    let errors = parser.finish(|offset, event| builder.event(offset, event)).unwrap();
+    (builder.finish(), errors)
+}
+
+/// Writes `nodes` back out as a stream. With no `indent` the output is
+/// compact and parses back to the same tree; otherwise each child goes on
+/// its own line, indented by `indent` spaces per level. Garbage is always
+/// written without escapes, since its contents never need any.
+pub fn pretty_print(nodes: &[Node], indent: Option<usize>) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        write_node(node, indent, 0, &mut out);
+        if indent.is_some() { out.push('\n') }
+    }
+    out
+}
+
+fn write_node(node: &Node, indent: Option<usize>, depth: usize, out: &mut String) {
+    match *node {
+        Node::Garbage { ref contents, .. } => {
+            out.push('<');
+            out.push_str(&String::from_utf8_lossy(contents));
+            out.push('>');
+        }
+        Node::Group { ref children, .. } if children.is_empty() => out.push_str("{}"),
+        Node::Group { ref children, .. } => {
+            out.push('{');
+            for (i, child) in children.iter().enumerate() {
+                if i > 0 { out.push(',') }
+                if let Some(width) = indent {
+                    out.push('\n');
+                    out.extend((0..width * (depth + 1)).map(|_| ' '));
+                }
+                write_node(child, indent, depth + 1, out);
+            }
+            if let Some(width) = indent {
+                out.push('\n');
+                out.extend((0..width * depth).map(|_| ' '));
+            }
+            out.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree() {
+        let input = b"{{<a!>b>},{<!!>}}";
+        let roots = parse(input).unwrap();
+        assert_eq!(roots, vec![Node::Group {
+            span: Span { start: 0, end: 17 },
+            children: vec![
+                Node::Group {
+                    span: Span { start: 1, end: 9 },
+                    children: vec![Node::Garbage { span: Span { start: 2, end: 8 }, contents: b"ab".to_vec() }],
+                },
+                Node::Group {
+                    span: Span { start: 10, end: 16 },
+                    children: vec![Node::Garbage { span: Span { start: 11, end: 15 }, contents: vec![] }],
+                },
+            ],
+        }]);
+    }
+
+    #[test]
+    fn printing() {
+        let roots = parse(b"{{<a!>b>},{<!!>},{}}").unwrap();
+        let compact = pretty_print(&roots, None);
+        assert_eq!(compact, "{{<ab>},{<>},{}}");
+        assert_eq!(pretty_print(&parse(compact.as_bytes()).unwrap(), None), compact);
+        let (recovered, errors) = parse_lenient(b"{{x<a>},{<b");
+        assert_eq!(pretty_print(&recovered, None), "{{},{<b>}}");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(pretty_print(&roots, Some(2)), "{\n  {\n    <ab>\n  },\n  {\n    <>\n  },\n  {}\n}\n");
+    }
+}
@@ -1,134 +1,64 @@
-fn main() {
-    let input = include_str!("../input.txt").trim();
-    println!("Hello, world!");
-
-    let result_one = part_one(&input);
-    println!("part one {}", result_one);
-
-    let result_two = part_two(&input);
-    println!("part two {}", result_two);
-}
-
+use std::env;
+use std::fs;
 
-#[derive(Debug, Clone)]
-/// The current parsing state
-enum State {
-    Ready,
-    Group,
-    Garbage,
-    Escape,
-}
+mod ast;
+mod stream;
 
-#[derive(Debug, Clone)]
-/// Transition operations
-enum Op {
-    Push(State),
-    Pop,
-    Continue,
-}
+use stream::tally_reader;
 
-impl State {
-    fn transition(&self, c: char) -> Result<Op, String> {
-        match *self {
-            State::Ready => {
-                match c {
-                    '{' => Ok(Op::Push(State::Group)),
-                    _ => Err(format!("unexpected char '{}' in state {:?}", c, self)),
-                }
-            }
-            State::Group => {
-                match c {
-                    '{' => Ok(Op::Push(State::Group)),
-                    '<' => Ok(Op::Push(State::Garbage)),
-                    '}' => Ok(Op::Pop),
-                    ',' => Ok(Op::Continue),
-                    _ => Err(format!("unexpected char '{}' in state {:?}", c, self)),
-                }
-            }
-            State::Garbage => {
-                match c {
-                    '>' => Ok(Op::Pop),
-                    '!' => Ok(Op::Push(State::Escape)),
-                    _ => Ok(Op::Continue),
-                }
-            }
-            State::Escape => Ok(Op::Pop),
-        }
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("--pretty") {
+        let strict = args.get(1).map(String::as_str) == Some("--strict");
+        let path = args.get(if strict { 2 } else { 1 }).cloned();
+        return pretty_print(path, strict);
     }
 
-    fn is_group(&self) -> bool {
-        match *self {
-            State::Group => true,
-            _ => false,
-        }
-    }
+    let input = include_str!("../input.txt").trim();
 
-    fn is_garbage(&self) -> bool {
-        match *self {
-            State::Garbage => true,
-            _ => false,
-        }
-    }
+    // both parts come out of a single pass over the stream
+    let result = tally_reader(input.as_bytes()).expect("malformed input");
+    println!("part one {}", result.score);
+    println!("part two {}", result.garbage);
 }
 
-/// Parse the stream one character at a time; increasing and decreasing
-/// the level tally with each group push/pop.
-fn part_one(input: &'static str) -> usize {
-    let mut stack = Vec::new();
-    let mut score = 0;
-    let mut level = 0;
-    for (i, c) in input.chars().enumerate() {
-        match stack.last().unwrap_or(&State::Ready).transition(c) {
-            Ok(Op::Push(state)) => {
-                if state.is_group() {
-                    level += 1;
-                    score += level;
-                }
-                stack.push(state);
+/// Prints the stream in `path` (or the puzzle input) as an indented tree.
+/// Unless `strict` is set, malformed input is reported and recovered from.
+fn pretty_print(path: Option<String>, strict: bool) {
+    let input = match path {
+        Some(path) => fs::read(&path).expect("failed to read input"),
+        None => include_bytes!("../input.txt").to_vec(),
+    };
+    let end = input.iter().rposition(|b| !b.is_ascii_whitespace()).map(|i| i + 1).unwrap_or(0);
+    let nodes = if strict {
+        match ast::parse(&input[..end]) {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
             }
-            Ok(Op::Pop) => {
-                let prev_state = stack.pop().unwrap();
-                if prev_state.is_group() {
-                    level -= 1;
-                }
-            }
-            Ok(Op::Continue) => continue,
-            Err(msg) => eprintln!("Error at char {}: {}", i, msg),
         }
-    }
-    score
-}
-
-/// Ditto part one, but here we only count occurances of `Op::Continue`
-/// while we're in a garbage group.
-fn part_two(input: &'static str) -> usize {
-    let mut stack = Vec::new();
-    let mut garbage = 0;
-    for (i, c) in input.chars().enumerate() {
-        let is_garbage = stack.last().unwrap_or(&State::Ready).is_garbage();
-        match stack.last().unwrap_or(&State::Ready).transition(c) {
-            Ok(Op::Push(state)) => {
-                stack.push(state);
-            }
-            Ok(Op::Pop) => {
-                stack.pop();
-            }
-            Ok(Op::Continue) => {
-                if is_garbage {
-                    garbage += 1;
-                }
-                continue
-            }
-            Err(msg) => eprintln!("Error at char {}: {}", i, msg),
+    } else {
+        let (nodes, errors) = ast::parse_lenient(&input[..end]);
+        for error in errors {
+            eprintln!("warning: {}", error);
         }
-    }
-    garbage
+        nodes
+    };
+    print!("{}", ast::pretty_print(&nodes, Some(2)));
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    fn part_one(input: &str) -> usize {
+        tally_reader(input.as_bytes()).unwrap().score
+    }
+
+    fn part_two(input: &str) -> usize {
+        tally_reader(input.as_bytes()).unwrap().garbage
+    }
+
     #[test]
     fn test_basic() {
         assert_eq!(part_one("{}"), 1);
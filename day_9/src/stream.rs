@@ -0,0 +1,397 @@
+//! A push-based parser for the group/garbage stream format.
+//!
+//! Input is fed in arbitrary chunks, and the parser reports what it sees
+//! as a sequence of `Event`s. Nothing is buffered beyond the nesting
+//! stack, so streams of any size can be processed in constant memory.
+
+use std::fmt;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, PartialEq)]
+/// The current parsing state
+enum State {
+    Ready,
+    Group,
+    Garbage,
+    Escape,
+}
+
+#[derive(Debug, Clone)]
+/// Transition operations
+enum Op {
+    Push(State),
+    Pop,
+    Continue,
+}
+
+/// Something the parser saw in the stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    GroupOpen,
+    GroupClose,
+    GarbageStart,
+    GarbageEnd,
+    /// A byte of garbage that was not cancelled.
+    GarbageByte(u8),
+    /// A `!` and the byte it cancelled.
+    Escape(u8),
+}
+
+impl State {
+    fn transition(&self, c: u8) -> Option<Op> {
+        match *self {
+            State::Ready => {
+                match c {
+                    b'{' => Some(Op::Push(State::Group)),
+                    c if c.is_ascii_whitespace() => Some(Op::Continue),
+                    _ => None,
+                }
+            }
+            State::Group => {
+                match c {
+                    b'{' => Some(Op::Push(State::Group)),
+                    b'<' => Some(Op::Push(State::Garbage)),
+                    b'}' => Some(Op::Pop),
+                    b',' => Some(Op::Continue),
+                    _ => None,
+                }
+            }
+            State::Garbage => {
+                match c {
+                    b'>' => Some(Op::Pop),
+                    b'!' => Some(Op::Push(State::Escape)),
+                    _ => Some(Op::Continue),
+                }
+            }
+            State::Escape => Some(Op::Pop),
+        }
+    }
+
+    /// What would have been accepted in this state.
+    fn expected(&self) -> &'static [&'static str] {
+        match *self {
+            State::Ready => &["'{'"],
+            State::Group => &["'{'", "'<'", "'}'", "','"],
+            State::Garbage => &["'>'", "'!'", "any byte"],
+            State::Escape => &["any byte"],
+        }
+    }
+}
+
+/// How the parser handles malformed input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Fail at the first error, and reject anything but whitespace after
+    /// the first top-level group.
+    Strict,
+    /// Record errors and resynchronise: unexpected bytes are skipped up to
+    /// the next `{` or `}`, and anything left open at the end is closed.
+    Lenient,
+}
+
+/// Something the parser was inside of when it hit an error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nesting {
+    /// A group opened at this byte offset.
+    Group(usize),
+    /// Garbage opened at this byte offset.
+    Garbage(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column, in bytes.
+    pub column: usize,
+    /// The offending byte, or `None` at end of input.
+    pub found: Option<u8>,
+    pub expected: &'static [&'static str],
+    /// Open groups and garbage, outermost first.
+    pub stack: Vec<Nesting>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {} (byte {}): ", self.line, self.column, self.offset)?;
+        match self.found {
+            Some(c) => write!(f, "unexpected {:?}", c as char)?,
+            None => write!(f, "unexpected end of input")?,
+        }
+        write!(f, ", expected {}", self.expected.join(" or "))?;
+        if let Some(inner) = self.stack.last() {
+            match *inner {
+                Nesting::Group(start) => write!(f, " in group opened at byte {}", start)?,
+                Nesting::Garbage(start) => write!(f, " in garbage opened at byte {}", start)?,
+            }
+            write!(f, " (depth {})", self.stack.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Parser {
+    mode: Mode,
+    /// Each open state, with the offset at which it was entered.
+    stack: Vec<(State, usize)>,
+    /// The number of bytes consumed so far.
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// Whether a top-level group has been closed.
+    seen_root: bool,
+    /// Set after an error in lenient mode, until we resynchronise.
+    skipping: bool,
+    errors: Vec<ParseError>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::with_mode(Mode::Strict)
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Parser::default()
+    }
+
+    pub fn with_mode(mode: Mode) -> Self {
+        Parser {
+            mode,
+            stack: Vec::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            seen_root: false,
+            skipping: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Consumes a chunk of input, calling `handler` with the offset and
+    /// kind of every event. In strict mode this stops at the first
+    /// unexpected byte; in lenient mode errors are collected instead.
+    pub fn feed<F>(&mut self, chunk: &[u8], mut handler: F) -> Result<(), ParseError>
+        where F: FnMut(usize, Event)
+    {
+        for &c in chunk {
+            let state = self.stack.last().map(|s| s.0.clone()).unwrap_or(State::Ready);
+            let op = match state.transition(c) {
+                _ if self.skipping && c != b'{' && c != b'}' => None,
+                Some(Op::Push(State::Group)) if self.mode == Mode::Strict && self.seen_root => None,
+                op => op,
+            };
+            match op {
+                Some(op) => {
+                    self.skipping = false;
+                    self.apply(op, c, &mut handler);
+                }
+                None if self.skipping => (),
+                None => {
+                    let expected = if self.seen_root && self.mode == Mode::Strict {
+                        &["end of input"]
+                    } else {
+                        state.expected()
+                    };
+                    let err = self.error(Some(c), expected);
+                    if self.mode == Mode::Strict {
+                        return Err(err)
+                    }
+                    self.errors.push(err);
+                    self.skipping = true;
+                    // a stray '}' at the top level can't resynchronise
+                    // anything, so just drop it.
+                    if c == b'}' { self.skipping = false }
+                }
+            }
+            self.offset += 1;
+            if c == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Signals the end of input, reporting anything left unclosed. In
+    /// lenient mode the open garbage and groups are closed (emitting their
+    /// events at the final offset) and all recorded errors are returned.
+    pub fn finish<F>(&mut self, mut handler: F) -> Result<Vec<ParseError>, ParseError>
+        where F: FnMut(usize, Event)
+    {
+        if let Some((state, _)) = self.stack.last() {
+            let err = self.error(None, state.expected());
+            if self.mode == Mode::Strict {
+                return Err(err)
+            }
+            self.errors.push(err);
+            while let Some((state, _)) = self.stack.pop() {
+                match state {
+                    State::Group => handler(self.offset, Event::GroupClose),
+                    State::Garbage => handler(self.offset, Event::GarbageEnd),
+                    _ => (),
+                }
+            }
+        }
+        Ok(::std::mem::take(&mut self.errors))
+    }
+
+    fn apply<F>(&mut self, op: Op, c: u8, handler: &mut F)
+        where F: FnMut(usize, Event)
+    {
+        let offset = self.offset;
+        match op {
+            Op::Push(State::Group) => {
+                self.stack.push((State::Group, offset));
+                handler(offset, Event::GroupOpen);
+            }
+            Op::Push(State::Garbage) => {
+                self.stack.push((State::Garbage, offset));
+                handler(offset, Event::GarbageStart);
+            }
+            Op::Push(state) => self.stack.push((state, offset)),
+            Op::Pop => match self.stack.pop().unwrap().0 {
+                State::Group => {
+                    if self.stack.is_empty() { self.seen_root = true }
+                    handler(offset, Event::GroupClose)
+                }
+                State::Garbage => handler(offset, Event::GarbageEnd),
+                State::Escape => handler(offset - 1, Event::Escape(c)),
+                State::Ready => unreachable!(),
+            },
+            Op::Continue if self.stack.last().map(|s| &s.0) == Some(&State::Garbage) =>
+                handler(offset, Event::GarbageByte(c)),
+            Op::Continue => (),
+        }
+    }
+
+    fn error(&self, found: Option<u8>, expected: &'static [&'static str]) -> ParseError {
+        let stack = self.stack.iter()
+            .filter_map(|&(ref state, start)| match *state {
+                State::Group => Some(Nesting::Group(start)),
+                State::Garbage => Some(Nesting::Garbage(start)),
+                _ => None,
+            })
+            .collect();
+        ParseError { offset: self.offset, line: self.line, column: self.column, found, expected, stack }
+    }
+}
+
+/// The puzzle's two answers, accumulated from events.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    /// The sum of every group's nesting depth.
+    pub score: usize,
+    /// The number of uncancelled garbage bytes.
+    pub garbage: usize,
+    depth: usize,
+}
+
+impl Tally {
+    pub fn event(&mut self, event: Event) {
+        match event {
+            Event::GroupOpen => {
+                self.depth += 1;
+                self.score += self.depth;
+            }
+            Event::GroupClose => self.depth -= 1,
+            Event::GarbageByte(_) => self.garbage += 1,
+            _ => (),
+        }
+    }
+}
+
+/// Computes score and garbage count in a single pass over a reader,
+/// parsing strictly.
+pub fn tally_reader<R: Read>(mut reader: R) -> io::Result<Tally> {
+    let mut parser = Parser::new();
+    let mut tally = Tally::default();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        parser.feed(&buf[..n], |_, event| tally.event(event))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    }
+    parser.finish(|_, event| tally.event(event))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(tally)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_across_chunks() {
+        let mut parser = Parser::new();
+        let mut events = Vec::new();
+        for chunk in &["{<a!", ">b>,", "{}}"] {
+            parser.feed(chunk.as_bytes(), |off, ev| events.push((off, ev))).unwrap();
+        }
+        assert_eq!(events, vec![
+            (0, Event::GroupOpen), (1, Event::GarbageStart), (2, Event::GarbageByte(b'a')),
+            (3, Event::Escape(b'>')), (5, Event::GarbageByte(b'b')), (6, Event::GarbageEnd),
+            (8, Event::GroupOpen), (9, Event::GroupClose), (10, Event::GroupClose),
+        ]);
+        assert_eq!(Parser::new().feed(b"{x}", |_, _| ()).unwrap_err().offset, 1);
+    }
+
+    #[test]
+    fn errors() {
+        let err = Parser::new().feed(b"{{<a>},\n {}}", |_, _| ()).unwrap_err();
+        assert_eq!((err.offset, err.line, err.column, err.found), (7, 1, 8, Some(b'\n')));
+        assert_eq!(err.stack, vec![Nesting::Group(0)]);
+        assert_eq!(err.to_string(), "line 1, column 8 (byte 7): unexpected '\\n', expected '{' or '<' or '}' or ',' in group opened at byte 0 (depth 1)");
+
+        // trailing content and unterminated input
+        assert_eq!(tally_str(b"{}{}", Mode::Strict).unwrap_err().expected, &["end of input"]);
+        let err = tally_str(b"{{<ab!>", Mode::Strict).unwrap_err();
+        assert_eq!((err.offset, err.found), (7, None));
+        assert_eq!(err.stack, vec![Nesting::Group(0), Nesting::Group(1), Nesting::Garbage(2)]);
+
+        // lenient mode skips to the next brace, and closes what's left open
+        let mut parser = Parser::with_mode(Mode::Lenient);
+        let mut tally = Tally::default();
+        parser.feed(b"}{{xy,z{}},<a>}{{", |_, ev| tally.event(ev)).unwrap();
+        let errors = parser.finish(|_, ev| tally.event(ev)).unwrap();
+        let offsets = errors.iter().map(|e| e.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0, 3, 17]);
+        assert_eq!(tally.score, 1 + 2 + 3 + 1 + 2);
+    }
+
+    fn tally_str(input: &[u8], mode: Mode) -> Result<Tally, ParseError> {
+        let mut parser = Parser::with_mode(mode);
+        let mut tally = Tally::default();
+        parser.feed(input, |_, ev| tally.event(ev))?;
+        parser.finish(|_, ev| tally.event(ev))?;
+        Ok(tally)
+    }
+
+    #[test]
+    fn reader() {
+        // a byte-at-a-time reader exercises chunk boundaries everywhere
+        struct Trickle<'a>(&'a [u8]);
+        impl<'a> Read for Trickle<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() || buf.is_empty() { return Ok(0) }
+                buf[0] = self.0[0];
+                self.0 = &self.0[1..];
+                Ok(1)
+            }
+        }
+        let input = b"{{<ab>},{<!!>},{<a!>},{<{o\"i!a,<{i<a>}}";
+        let tally = tally_reader(Trickle(input)).unwrap();
+        assert_eq!((tally.score, tally.garbage), (7, 17));
+        assert_eq!(tally_reader(&input[..]).unwrap(), tally);
+    }
+}
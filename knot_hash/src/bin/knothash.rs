@@ -0,0 +1,179 @@
+//! Computes knot hashes of files, stdin or strings, in the style of
+//! `sha256sum`.
+
+extern crate knot_hash;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process;
+
+use knot_hash::{knot_hash, to_hex, KnotHasher};
+
+static USAGE: &str = "\
+usage: knothash [FILE]...
+       knothash -s STRING...
+       knothash -c [LISTING]
+       knothash --sparse LENGTHS [--size N]
+
+With no FILE, or when FILE is -, read standard input.
+
+  -s, --string    hash each argument as a literal string
+  -c, --check     read hashes and file names from LISTING (or stdin) and
+                  check them
+      --sparse    run a single round with a comma-separated list of
+                  lengths, and print the product of the first two marks
+      --size      the ring size for --sparse (default 256)
+  -h, --help      show this message";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.first().map(String::as_str) {
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        Some("-s") | Some("--string") => {
+            for s in &args[1..] {
+                println!("{}  \"{}\"", to_hex(&knot_hash::hash(s.as_bytes())), s);
+            }
+            Ok(true)
+        }
+        Some("-c") | Some("--check") => check(args.get(1).map(String::as_str).unwrap_or("-")),
+        Some("--sparse") => sparse(&args[1..]),
+        _ => hash_files(&args),
+    };
+
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("knothash: {}", e);
+            eprintln!("try 'knothash --help' for usage");
+            process::exit(2);
+        }
+    }
+}
+
+fn open(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+fn hash_reader<R: Read>(mut reader: R) -> io::Result<[u8; 16]> {
+    let mut hasher = KnotHasher::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Prints a hash line for each path. Returns `Ok(false)` if any couldn't
+/// be read.
+fn hash_files(paths: &[String]) -> Result<bool, String> {
+    let stdin = ["-".to_owned()];
+    let paths = if paths.is_empty() { &stdin[..] } else { paths };
+    let mut all_ok = true;
+    for path in paths {
+        match open(path).and_then(hash_reader) {
+            Ok(digest) => println!("{}  {}", to_hex(&digest), path),
+            Err(e) => {
+                eprintln!("knothash: {}: {}", path, e);
+                all_ok = false;
+            }
+        }
+    }
+    Ok(all_ok)
+}
+
+/// Verifies each `HASH  FILE` line in the listing at `path`.
+fn check(path: &str) -> Result<bool, String> {
+    let listing = open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let (mut failed, mut unreadable, mut malformed) = (0, 0, 0);
+    for (i, line) in BufReader::new(listing).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {}", path, e))?;
+        if line.trim().is_empty() { continue }
+        let (expected, file) = match parse_check_line(&line) {
+            Some(parsed) => parsed,
+            None => {
+                eprintln!("knothash: {}: {}: improperly formatted line", path, i + 1);
+                malformed += 1;
+                continue
+            }
+        };
+        match open(file).and_then(hash_reader) {
+            Ok(ref digest) if to_hex(digest) == expected => println!("{}: OK", file),
+            Ok(_) => {
+                println!("{}: FAILED", file);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("knothash: {}: {}", file, e);
+                println!("{}: FAILED open or read", file);
+                unreadable += 1;
+            }
+        }
+    }
+    if malformed > 0 { eprintln!("knothash: WARNING: {} line(s) improperly formatted", malformed) }
+    if unreadable > 0 { eprintln!("knothash: WARNING: {} listed file(s) could not be read", unreadable) }
+    if failed > 0 { eprintln!("knothash: WARNING: {} computed hash(es) did NOT match", failed) }
+    Ok(failed + unreadable + malformed == 0)
+}
+
+/// Splits a listing line into its lowercased hash and file name.
+fn parse_check_line(line: &str) -> Option<(String, &str)> {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let hash = parts.next()?;
+    // sha256sum marks binary-mode files with a '*'; we don't care which
+    let file = parts.next()?.trim_start_matches(' ');
+    let file = if let Some(stripped) = file.strip_prefix('*') { stripped } else { file };
+    if hash.len() != 32 || !hash.chars().all(|c| c.is_ascii_hexdigit()) || file.is_empty() {
+        return None
+    }
+    Some((hash.to_ascii_lowercase(), file))
+}
+
+/// Runs a single round of the lengths in `args[0]`, like day ten's part one.
+fn sparse(args: &[String]) -> Result<bool, String> {
+    let lengths = args.first().ok_or("--sparse expects a list of lengths")?;
+    let size = match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("--size"), Some(n)) => n.parse::<usize>().map_err(|e| format!("bad size: {}", e))?,
+        (None, _) => 256,
+        _ => return Err("unexpected arguments after lengths".into()),
+    };
+    if size < 2 {
+        return Err("size must be at least 2".into())
+    }
+    let lengths = lengths.split(',')
+        .map(|l| l.trim().parse::<usize>().map_err(|e| format!("bad length '{}': {}", l, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(l) = lengths.iter().find(|&&l| l > size) {
+        return Err(format!("length {} is longer than the ring ({})", l, size))
+    }
+    let marks = (0..size).collect::<Vec<_>>();
+    let result = knot_hash(&marks, &lengths, 0, 0).data;
+    println!("{}", result[0] * result[1]);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_lines() {
+        assert_eq!(parse_check_line("33EFEB34EA91902BB2F59C9920CAA6CD  a file.txt"),
+                   Some(("33efeb34ea91902bb2f59c9920caa6cd".to_owned(), "a file.txt")));
+        assert_eq!(parse_check_line("a2582a3a0e66e6e86e3812dcb672a272 *bin"),
+                   Some(("a2582a3a0e66e6e86e3812dcb672a272".to_owned(), "bin")));
+        assert_eq!(parse_check_line("a2582a3a  short"), None);
+        assert_eq!(parse_check_line("a2582a3a0e66e6e86e3812dcb672a272"), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! The knot hash from days 10 and 14, as a reusable hasher.
+//!
+//! ```
+//! use knot_hash::{KnotHasher, to_hex};
+//!
+//! let mut hasher = KnotHasher::new();
+//! hasher.update(b"AoC ");
+//! hasher.update(b"2017");
+//! assert_eq!(to_hex(&hasher.finalize()), "33efeb34ea91902bb2f59c9920caa6cd");
+//! ```
+
+use std::hash::{BuildHasher, Hasher};
+
+/// The suffix appended to every input before hashing.
+pub const STANDARD_SALT: [u8; 5] = [17, 31, 73, 47, 23];
+
+/// Parameters for a `KnotHasher`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The number of marks on the ring. Must be a multiple of 16, no
+    /// larger than 256, so that the dense hash is always 16 bytes.
+    pub ring_size: usize,
+    /// How many times the length sequence is applied.
+    pub rounds: usize,
+    /// Lengths appended to the input before hashing.
+    pub salt: Vec<u8>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { ring_size: 256, rounds: 64, salt: STANDARD_SALT.to_vec() }
+    }
+}
+
+pub struct KnotResult<T> {
+    pub data: Vec<T>,
+    pub final_pos: usize,
+    pub skip_size: usize,
+}
+
+/// Runs a single round of the knot over `input`, starting from the given
+/// position and skip size.
+pub fn knot_hash<T: Copy>(input: &[T],
+                          lengths: &[usize],
+                          skip_size: usize,
+                          start_pos: usize) -> KnotResult<T> {
+    let mut input = input.to_owned();
+    let mut skip_size = skip_size;
+    let mut cur_pos = start_pos;
+    let input_len = input.len();
+
+    for len in lengths {
+        let start = cur_pos % input_len ;
+        let end = (cur_pos + len) % input_len;
+        if end <= start {
+            input.rotate_left(start);
+            {
+                let sub_v = &mut input[0..*len];
+                sub_v.reverse();
+            }
+            input.rotate_right(start);
+        } else {
+            let sub_v = &mut input[start..end];
+            sub_v.reverse();
+        }
+        cur_pos += len + skip_size;
+        skip_size += 1;
+    }
+    let data = input;
+    let final_pos = cur_pos;
+    KnotResult { data, final_pos, skip_size }
+}
+
+/// A 256-mark ring, for running rounds without allocating.
+///
+/// This produces exactly the same results as `knot_hash` on `0..256`, but
+/// reverses wrapping spans in place rather than rotating the whole ring.
+#[derive(Clone)]
+pub struct Ring {
+    pub marks: [u8; 256],
+    pos: usize,
+    skip: usize,
+}
+
+impl Default for Ring {
+    fn default() -> Self {
+        let mut marks = [0u8; 256];
+        for (i, m) in marks.iter_mut().enumerate() {
+            *m = i as u8;
+        }
+        Ring { marks, pos: 0, skip: 0 }
+    }
+}
+
+impl Ring {
+    pub fn new() -> Self {
+        Ring::default()
+    }
+
+    /// Applies one round of `lengths`, carrying position and skip size
+    /// over from any previous rounds.
+    #[inline]
+    pub fn round<I>(&mut self, lengths: I) where I: IntoIterator<Item=u8> {
+        for len in lengths {
+            let len = len as usize;
+            // positions only matter mod 256, so let them wrap
+            let mut lo = self.pos;
+            let mut hi = self.pos + len;
+            while lo + 1 < hi {
+                hi -= 1;
+                self.marks.swap(lo & 0xff, hi & 0xff);
+                lo += 1;
+            }
+            self.pos = (self.pos + len + self.skip) & 0xff;
+            self.skip += 1;
+        }
+    }
+
+    pub fn dense(&self) -> [u8; 16] {
+        let mut out = [0u8; 16];
+        for (o, chunk) in out.iter_mut().zip(self.marks.chunks(16)) {
+            *o = chunk.iter().fold(0, |ac, i| ac ^ i);
+        }
+        out
+    }
+}
+
+/// Computes the dense knot hash of everything passed to `update`.
+///
+/// Each round needs the whole length sequence, so input is buffered until
+/// `finalize` is called.
+#[derive(Debug, Clone)]
+pub struct KnotHasher {
+    config: Config,
+    input: Vec<u8>,
+}
+
+impl Default for KnotHasher {
+    fn default() -> Self {
+        KnotHasher::with_config(Config::default())
+    }
+}
+
+impl KnotHasher {
+    pub fn new() -> Self {
+        KnotHasher::default()
+    }
+
+    /// Panics if `config.ring_size` is not a multiple of 16 in `16..=256`.
+    /// With a ring smaller than 256, hashing an input (or salt) byte larger
+    /// than the ring size also panics, since it isn't a valid length.
+    pub fn with_config(config: Config) -> Self {
+        assert!(config.ring_size > 0 && config.ring_size <= 256 && config.ring_size.is_multiple_of(16),
+                "invalid ring size {}", config.ring_size);
+        KnotHasher { config, input: Vec::new() }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    /// The ring after all rounds, before it is reduced.
+    pub fn sparse(&self) -> Vec<u8> {
+        let lengths = self.input.iter()
+            .chain(self.config.salt.iter())
+            .map(|&b| b as usize)
+            .collect::<Vec<_>>();
+        assert!(lengths.iter().all(|&l| l <= self.config.ring_size),
+                "length longer than ring size {}", self.config.ring_size);
+        if self.config.ring_size == 256 {
+            return self.ring().marks.to_vec()
+        }
+        let mut val = (0..self.config.ring_size).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut cur_pos = 0;
+        let mut skip_size = 0;
+        for _ in 0..self.config.rounds {
+            let result = knot_hash(&val, &lengths, skip_size, cur_pos);
+            val = result.data;
+            cur_pos = result.final_pos;
+            skip_size = result.skip_size;
+        }
+        val
+    }
+
+    fn ring(&self) -> Ring {
+        let mut ring = Ring::new();
+        for _ in 0..self.config.rounds {
+            ring.round(self.input.iter().chain(self.config.salt.iter()).cloned());
+        }
+        ring
+    }
+
+    /// The dense hash: the sparse ring xor-reduced in 16 equal blocks.
+    pub fn finalize(&self) -> [u8; 16] {
+        if self.config.ring_size == 256 {
+            return self.ring().dense()
+        }
+        let sparse = self.sparse();
+        let mut out = [0u8; 16];
+        for (o, chunk) in out.iter_mut().zip(sparse.chunks(self.config.ring_size / 16)) {
+            *o = chunk.iter().fold(0, |ac, i| ac ^ i);
+        }
+        out
+    }
+}
+
+impl Hasher for KnotHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    /// The first eight bytes of the digest, as a little-endian integer.
+    fn finish(&self) -> u64 {
+        let digest = self.finalize();
+        digest[..8].iter().rev().fold(0, |acc, &b| acc << 8 | b as u64)
+    }
+}
+
+/// Creates `KnotHasher`s, for use with `HashMap` and friends.
+#[derive(Debug, Clone, Default)]
+pub struct BuildKnotHasher {
+    pub config: Config,
+}
+
+impl BuildHasher for BuildKnotHasher {
+    type Hasher = KnotHasher;
+    fn build_hasher(&self) -> KnotHasher {
+        KnotHasher::with_config(self.config.clone())
+    }
+}
+
+/// The standard knot hash of `bytes`.
+pub fn hash(bytes: &[u8]) -> [u8; 16] {
+    let mut hasher = KnotHasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Formats a digest as lowercase hex.
+pub fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(to_hex(&hash(b"")), "a2582a3a0e66e6e86e3812dcb672a272");
+        assert_eq!(to_hex(&hash(b"AoC 2017")), "33efeb34ea91902bb2f59c9920caa6cd");
+        assert_eq!(to_hex(&hash(b"1,2,3")), "3efbe78a8d82f29979031a4aa0b16a9d");
+        assert_eq!(to_hex(&hash(b"1,2,4")), "63960835bcdc130f0b66d7ff4f6a5a8e");
+    }
+
+    #[test]
+    fn config() {
+        let sample = knot_hash(&[0, 1, 2, 3, 4], &[3, 4, 1, 5], 0, 0).data;
+        assert_eq!(sample, vec![3, 4, 2, 1, 0]);
+
+        let mut hasher = KnotHasher::with_config(Config { ring_size: 32, rounds: 1, salt: vec![] });
+        hasher.update(&[3, 4, 1, 5]);
+        let mut expected = knot_hash(&(0..32).collect::<Vec<u8>>(), &[3, 4, 1, 5], 0, 0).data;
+        assert_eq!(hasher.sparse(), expected);
+        expected = expected.chunks(2).map(|c| c[0] ^ c[1]).collect();
+        assert_eq!(hasher.finalize().to_vec(), expected);
+    }
+
+    #[test]
+    fn ring_matches_generic() {
+        // a little LCG, so we get the same "random" inputs every time
+        let mut seed = 12345u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        };
+        let mut generic = (0..=255u8).collect::<Vec<_>>();
+        let (mut pos, mut skip) = (0, 0);
+        let mut ring = Ring::new();
+        for _ in 0..200 {
+            let lengths = (0..next() % 40).map(|_| next()).collect::<Vec<_>>();
+            let result = knot_hash(&generic, &lengths.iter().map(|&l| l as usize).collect::<Vec<_>>(),
+                                   skip, pos);
+            generic = result.data;
+            pos = result.final_pos;
+            skip = result.skip_size;
+            ring.round(lengths.iter().cloned());
+            assert_eq!(&ring.marks[..], &generic[..]);
+        }
+    }
+
+    #[test]
+    fn build_hasher() {
+        let mut map = HashMap::with_hasher(BuildKnotHasher::default());
+        map.insert("flqrgnkx", 8108);
+        map.insert("hxtvlmkl", 8214);
+        assert_eq!(map.get("flqrgnkx"), Some(&8108));
+
+        let mut hasher = KnotHasher::new();
+        hasher.write(b"AoC 2017");
+        assert_eq!(hasher.finish(), 0x2b9091ea34ebef33);
+    }
+}